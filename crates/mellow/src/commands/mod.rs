@@ -7,8 +7,10 @@ pub mod syncing;
 
 pub static COMMANDS: Lazy<Vec<Command>> = Lazy::new(|| vec![
 	server::setup(),
+	server::rankbinds(),
 	
 	syncing::sync(),
 	syncing::forcesync(),
-	syncing::forcesyncall()
+	syncing::forcesyncall(),
+	syncing::cancelsyncall()
 ]);
\ No newline at end of file
@@ -3,9 +3,14 @@ use mellow_macros::command;
 use mellow_models::mellow::ServerModel;
 use mellow_util::{ DISCORD_INTERACTION_CLIENT, PG_POOL };
 use std::pin::Pin;
-use twilight_model::id::Id;
+use twilight_model::{
+	application::command::CommandOption,
+	id::Id
+};
+use twilight_util::builder::command::{ BooleanBuilder, IntegerBuilder };
 
 use crate::{
+	syncing::rank_binds::RankBindPlan,
 	Result, Context, Interaction, CommandResponse
 };
 
@@ -27,7 +32,7 @@ pub async fn setup(_context: Context, interaction: Interaction) -> Result<Comman
 				"
 				INSERT INTO mellow_servers (id, name, creator_id, owner_user_id, avatar_url, banner_url)
 				VALUES ($1, $2, $3, $3, $4, $5)
-				RETURNING logging_types, default_nickname, logging_channel_id, allow_forced_syncing
+				RETURNING logging_types, default_nickname, logging_channel_id, allow_forced_syncing, auto_sync_members, resync_interval_minutes, removal_message, unlinked_member_policy
 				",
 				guild_id.get() as i64,
 				guild.name,
@@ -45,7 +50,11 @@ pub async fn setup(_context: Context, interaction: Interaction) -> Result<Comman
 					logging_types: record.logging_types as u8,
 					default_nickname: record.default_nickname,
 					logging_channel_id: record.logging_channel_id.map(|x| Id::new(x as u64)),
-					allow_forced_syncing: record.allow_forced_syncing
+					allow_forced_syncing: record.allow_forced_syncing,
+					auto_sync_members: record.auto_sync_members,
+					resync_interval_minutes: record.resync_interval_minutes.map(|x| x as u32),
+					removal_message: record.removal_message.and_then(|x| serde_json::from_value(x).ok()),
+					unlinked_member_policy: record.unlinked_member_policy.and_then(|x| serde_json::from_value(x).ok())
 				});
 
 			DISCORD_INTERACTION_CLIENT
@@ -59,4 +68,48 @@ pub async fn setup(_context: Context, interaction: Interaction) -> Result<Comman
 			format!("## Account not connected\nIt appears I do not recognise your wondrous face, you must be new!\n* Do you have a HAKUMI Account? If so, follow these [instructions](<https://hakumi.cafe/docs/platform/account/connections>), and then execute this command again.\n* If you're completely new, never heard of a HAKUMI, or even a measily marshmellow, simply [tap here](<https://discord.com/api/oauth2/authorize?client_id=1068554282481229885&redirect_uri=https%3A%2F%2Fapi.hakumi.cafe%2Fv0%2Fauth%2Fcallback%2Fmellow&response_type=code&scope=identify&state=setup_{guild_id}>)!")
 		)
 	})
-}
\ No newline at end of file
+}
+
+fn rankbinds_options() -> Vec<CommandOption> {
+	vec![
+		IntegerBuilder::new("group_id", "The ID of the Roblox group to bind ranks of.")
+			.min_value(1)
+			.required(true)
+			.build(),
+		BooleanBuilder::new("apply", "Create the roles and sync actions, instead of only showing what would be created.")
+			.build()
+	]
+}
+
+#[tracing::instrument(name = "commands::rankbinds", skip_all)]
+#[command(slash, no_dm, description = "Generate sync actions that give members the role of their Roblox group rank.", default_member_permissions = "0", options = "rankbinds_options")]
+pub async fn rankbinds(_context: Context, interaction: Interaction) -> Result<CommandResponse> {
+	let guild_id = interaction.guild_id.unwrap();
+	if !CACHE.mellow.servers.contains_key(&guild_id) {
+		return Ok(CommandResponse::ephemeral(
+			"## <:niko_look_left:1227198516590411826>  Cannot bind ranks\nThis server hasn't been set up with mellow yet, if you're an administrator, execute the /setup command."
+		));
+	}
+
+	let group_id = interaction.integer_option("group_id").unwrap() as u64;
+	let is_applying = interaction.boolean_option("apply").unwrap_or_default();
+	Ok(CommandResponse::defer(interaction.token.clone(), Box::pin(async move {
+		let mut plan = RankBindPlan::generate(guild_id, group_id)
+			.await?;
+		let content = if plan.binds.is_empty() {
+			"## <:niko_look_left:1227198516590411826>  Nothing to bind\nThat group doesn't have any ranks besides guest.".into()
+		} else if is_applying {
+			let total_created = plan.apply(guild_id)
+				.await?;
+			format!("## Ranks bound\n{total_created} sync action(s) were created, members will receive their rank's role the next time they sync.\n{}", plan.display())
+		} else {
+			format!("## Rank binds preview\nNothing has been created yet, run this command again with `apply` to create the following.\n{}", plan.display())
+		};
+		DISCORD_INTERACTION_CLIENT
+			.update_response(&interaction.token)
+			.content(Some(&content))
+			.await?;
+
+		Ok(())
+	})))
+}
@@ -1,10 +1,6 @@
-use dashmap::DashMap;
 use mellow_cache::CACHE;
 use mellow_macros::command;
-use mellow_models::{
-	hakumi::user::connection::ConnectionKind,
-	mellow::ServerModel
-};
+use mellow_models::mellow::server::SyncHistoryModel;
 use mellow_util::{
 	hakuid::{
 		marker::UserMarker as HakuUserMarker,
@@ -13,30 +9,51 @@ use mellow_util::{
 	create_website_token,
 	DISCORD_INTERACTION_CLIENT
 };
-use tokio::time;
 use twilight_model::{
 	id::{ marker::{ GuildMarker, UserMarker }, Id },
-	application::interaction::InteractionData
+	application::{
+		command::CommandOption,
+		interaction::InteractionData
+	}
 };
+use twilight_util::builder::command::{ BooleanBuilder, UserBuilder };
 
 use crate::{
 	server::logging::{ ServerLog, send_logs },
 	syncing::{
+		bulk,
+		rollback::{ RollbackResult, rollback_sync, rollback_sync_job },
 		sign_ups::create_sign_up,
-		RoleChangeKind, SyncingInitiator, SyncingIssue, SyncMemberResult,
-		sync_member, get_connection_metadata, sync_single_user
+		MemberStatus, RoleChangeKind, SyncingInitiator, SyncingIssue, SyncMemberResult,
+		sync_single_user
 	},
 	Result, Context, Interaction, CommandResponse,
 	cast
 };
 
+#[allow(clippy::too_many_arguments)]
 #[tracing::instrument]
-pub async fn sync_with_token(guild_id: Id<GuildMarker>, user_id: HakuId<HakuUserMarker>, member_id: Id<UserMarker>, interaction_token: &String, is_onboarding: bool, forced_by: Option<Id<UserMarker>>) -> Result<SyncMemberResult> {
+pub async fn sync_with_token(guild_id: Id<GuildMarker>, user_id: HakuId<HakuUserMarker>, member_id: Id<UserMarker>, interaction_token: &String, is_onboarding: bool, forced_by: Option<Id<UserMarker>>, is_dry_run: bool, with_trace: bool) -> Result<SyncMemberResult> {
 	let initiator = match forced_by {
 		Some(x) => SyncingInitiator::ForcedBy(x),
 		None => SyncingInitiator::Manual
 	};
-	let result = sync_single_user(guild_id, user_id, member_id, initiator, None).await?;
+	let result = sync_single_user(guild_id, user_id, member_id, initiator, None, is_dry_run, with_trace).await?;
+	if is_dry_run {
+		let content = match &result.trace {
+			Some(trace) => format!("## <:mellow_squircled:1225413361777508393>  Why your profile looks like this
+Nothing has been changed, this is how every action was checked.
+{}", trace.display()),
+			None => preview_content(&result)
+		};
+		DISCORD_INTERACTION_CLIENT
+			.update_response(interaction_token)
+			.content(Some(&content))
+			.await?;
+
+		return Ok(result);
+	}
+
 	let mut has_assigned_role = false;
 	let mut has_retracted_role = false;
 	for item in result.role_changes.iter() {
@@ -119,18 +136,59 @@ pub async fn sync_with_token(guild_id: Id<GuildMarker>, user_id: HakuId<HakuUser
 	Ok(result)
 }
 
+fn preview_content(result: &SyncMemberResult) -> String {
+	let mut changes: Vec<String> = result.role_changes
+		.iter()
+		.map(|x| match x.kind {
+			RoleChangeKind::Added => format!("+ {}", x.display_name),
+			RoleChangeKind::Removed => format!("- {}", x.display_name)
+		})
+		.collect();
+	if let Some(nickname_change) = &result.nickname_change {
+		changes.push(format!("~ Nickname {} → {}",
+			nickname_change.0.as_deref().unwrap_or("(none)"),
+			nickname_change.1.as_deref().unwrap_or("(none)")
+		));
+	}
+	if let Some(timeout_until) = result.timeout_until {
+		changes.push(format!("~ Timed out until {}", timeout_until.format("%Y-%m-%d %H:%M UTC")));
+	}
+	match result.member_status {
+		MemberStatus::Banned => changes.push("- Banned from the server".into()),
+		MemberStatus::Kicked => changes.push("- Kicked from the server".into()),
+		MemberStatus::Ok => ()
+	}
+
+	if changes.is_empty() {
+		"## <:mellow_squircled:1225413361777508393>  Sync preview\nNothing would change if you synced right now, you're all set!".into()
+	} else {
+		format!("## <:mellow_squircled:1225413361777508393>  Sync preview\nNothing has been changed yet, this is what would happen if you synced right now.\n```diff\n{}```", changes.join("\n"))
+	}
+}
+
+fn sync_options() -> Vec<CommandOption> {
+	vec![
+		BooleanBuilder::new("preview", "Show what would change, without actually changing anything.")
+			.build(),
+		BooleanBuilder::new("explain", "Explain why each action was or wasn't met, without actually changing anything.")
+			.build()
+	]
+}
+
 // TODO: allow users to sync in dms via some sort of server selection
 #[tracing::instrument(name = "commands::sync", skip_all)]
-#[command(slash, no_dm, description = "Sync your server profile. (may contain traces of burgers)")]
+#[command(slash, no_dm, description = "Sync your server profile. (may contain traces of burgers)", options = "sync_options")]
 pub async fn sync(_context: Context, interaction: Interaction) -> Result<CommandResponse> {
 	let member = interaction.member().await?.unwrap();
 	let guild_id = interaction.guild_id.unwrap();
 	let member_id = member.user_id;
+	let is_explaining = interaction.boolean_option("explain").unwrap_or_default();
+	let is_dry_run = is_explaining || interaction.boolean_option("preview").unwrap_or_default();
 	if let Some(user_id) = CACHE.hakumi.user_by_discord(guild_id, member_id).await? {
 		return Ok(CommandResponse::defer(
 			interaction.token.clone(),
 			Box::pin(async move {
-				sync_with_token(guild_id, user_id, member_id, &interaction.token, false, None).await?;
+				sync_with_token(guild_id, user_id, member_id, &interaction.token, false, None, is_dry_run, is_explaining).await?;
 				Ok(())
 			})
 		));
@@ -174,7 +232,7 @@ pub async fn forcesync(_context: Context, interaction: Interaction) -> Result<Co
 	let member_id = resolved.members.into_iter().next().unwrap().0;
 	if let Some(user_id) = CACHE.hakumi.user_by_discord(guild_id, member_id).await? {
 		return Ok(CommandResponse::defer(interaction.token.clone(), Box::pin(async move {
-			sync_with_token(guild_id, user_id, member_id, &interaction.token, false, Some(interaction.user_id.unwrap())).await?;
+			sync_with_token(guild_id, user_id, member_id, &interaction.token, false, Some(interaction.user_id.unwrap()), false, false).await?;
 			Ok(())
 		})));
 	}
@@ -195,65 +253,107 @@ pub async fn forcesyncall(context: Context, interaction: Interaction) -> Result<
 		return forceful_disabled_response(guild_id);
 	}
 
+	// claimed straight away, so the same command used twice in a row doesn't start two jobs.
+	let Some(claim) = bulk::claim_job(guild_id) else {
+		return Ok(CommandResponse::ephemeral(
+			"## <:niko_look_left:1227198516590411826>  Cannot sync members\nEvery member in this server is already being synced, use `/cancelsyncall` if you'd like to stop it."
+		));
+	};
+
 	Ok(CommandResponse::defer(interaction.token.clone(), Box::pin(async move {
-		let user_ids = ServerModel::users(guild_id)
-			.await?;
-		let user_connection_ids = CACHE
-			.hakumi
-			.user_connections(&user_ids)
-			.await?;
-		let user_connections = CACHE
-			.hakumi
-			.connections(&user_connection_ids)
-			.await?;
-		let discord_user_ids = user_connections
-			.iter()
-			.filter_map(|x| if x.kind == ConnectionKind::Discord {
-				Some(Id::new(x.sub.parse().unwrap()))
-			} else { None })
-			.collect();
-		let mapped_user_ids: DashMap<Id<UserMarker>, HakuId<HakuUserMarker>> = DashMap::with_capacity(user_ids.len());
-		for connection in user_connections {
-			if connection.is_discord() {
-				mapped_user_ids.insert(Id::new(connection.sub.parse().unwrap()), connection.user_id);
-			}
-		}
+		let member_ids: Vec<_> = if bulk::syncs_unlinked_members(guild_id) {
+			bulk::server_member_ids(guild_id)
+				.await?
+		} else {
+			let (_, mapped_user_ids) = bulk::server_user_ids(guild_id)
+				.await?;
+			context
+				.members(guild_id, mapped_user_ids.into_keys().collect())
+				.await?
+				.into_iter()
+				.map(|x| x.user_id)
+				.collect()
+		};
 
-		let metadata = get_connection_metadata(guild_id, &user_ids)
+		DISCORD_INTERACTION_CLIENT
+			.update_response(&interaction.token)
+			.content(Some(&format!("## Syncing profiles... 0/{}", member_ids.len())))
 			.await?;
-		let members: Vec<_> = context
-			.members(guild_id, discord_user_ids)
-			.await?
-			.into_iter()
-			.map(|x| x.user_id)
-			.collect();
 
-		let mut logs: Vec<ServerLog> = vec![];
-		let mut total_synced = 0;
-		let mut total_changed = 0;
-		for user_id in members {
-			let result = sync_member(guild_id, mapped_user_ids.remove(&user_id).map(|x| x.1), user_id, SyncingInitiator::ForcedBy(interaction.user_id.unwrap()), &metadata)
-				.await?;
-			if let Some(result_log) = result.create_log() {
-				logs.push(result_log);
-			}
+		bulk::start_job(claim, interaction.user_id.unwrap(), interaction.token, member_ids)
+			.await
+	})))
+}
 
-			if result.profile_changed {
-				// sleep for one second to avoid hitting Discord ratelimit
-				time::sleep(time::Duration::from_secs(1)).await;
-				total_changed += 1;
-			}
+#[tracing::instrument(name = "commands::cancelsyncall", skip_all)]
+#[command(slash, no_dm, description = "Cancel the forceful sync currently running in this server.", default_member_permissions = "0")]
+pub async fn cancelsyncall(_context: Context, interaction: Interaction) -> Result<CommandResponse> {
+	let guild_id = interaction.guild_id.unwrap();
+	Ok(CommandResponse::ephemeral(if bulk::cancel_job(guild_id).await? {
+		"## Cancelling sync\nMembers that have already been synced will keep their changes."
+	} else {
+		"## <:niko_look_left:1227198516590411826>  Nothing to cancel\nThere isn't a forceful sync running in this server right now."
+	}))
+}
 
-			total_synced += 1
-		}
+fn rollbacksync_options() -> Vec<CommandOption> {
+	vec![
+		UserBuilder::new("member", "Only roll back the last sync of this member, instead of the last forceful sync of everyone.")
+			.build()
+	]
+}
+
+#[tracing::instrument(name = "commands::rollbacksync", skip_all)]
+#[command(slash, no_dm, description = "Undo the changes made by the last forceful sync of this server, or the last sync of a member.", default_member_permissions = "0", options = "rollbacksync_options")]
+pub async fn rollbacksync(_context: Context, interaction: Interaction) -> Result<CommandResponse> {
+	let guild_id = interaction.guild_id.unwrap();
+	if bulk::is_job_running(guild_id) {
+		return Ok(CommandResponse::ephemeral(
+			"## <:niko_look_left:1227198516590411826>  Cannot roll back\nEvery member in this server is currently being synced, wait for it to finish or use `/cancelsyncall` first."
+		));
+	}
+
+	let initiator_id = interaction.user_id;
+	let member_id = interaction.user_option("member");
+	Ok(CommandResponse::defer(interaction.token.clone(), Box::pin(async move {
+		let (results, total_failed) = if let Some(member_id) = member_id {
+			match SyncHistoryModel::get_latest_for_member(guild_id, member_id).await? {
+				Some(mut entry) => (vec![rollback_sync(guild_id, &mut entry).await?], 0),
+				None => (vec![], 0)
+			}
+		} else {
+			match SyncHistoryModel::latest_sync_job_id(guild_id).await? {
+				Some(sync_job_id) => rollback_sync_job(guild_id, sync_job_id).await?,
+				None => (vec![], 0)
+			}
+		};
 
 		DISCORD_INTERACTION_CLIENT
 			.update_response(&interaction.token)
-			.content(Some(&format!("## Successfully synced {total_synced} profiles\n{total_changed} profile(s) in total were updated.")))
+			.content(Some(&rollback_content(&results, total_failed)))
 			.await?;
 
-		send_logs(guild_id, logs)
-			.await?;
-		Ok(())
+		send_logs(guild_id, results
+			.iter()
+			.filter_map(|x| x.create_log(initiator_id))
+			.collect()
+		).await
 	})))
-}
\ No newline at end of file
+}
+
+fn rollback_content(results: &[RollbackResult], total_failed: u32) -> String {
+	if results.is_empty() && total_failed == 0 {
+		return "## <:niko_look_left:1227198516590411826>  Nothing to roll back\nThere aren't any syncs left that can be rolled back.".into();
+	}
+
+	let total_changed = results
+		.iter()
+		.filter(|x| x.changed())
+		.count();
+	let mut content = format!("## Rolled back {} sync(s)\n{total_changed} profile(s) were reverted, anything that has changed again since was left as-is.", results.len());
+	if total_failed > 0 {
+		content.push_str(&format!("\n{total_failed} profile(s) couldn't be rolled back."));
+	}
+
+	content
+}
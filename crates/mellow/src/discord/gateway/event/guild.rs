@@ -6,7 +6,10 @@ use twilight_model::{
 	id::{ marker::GuildMarker, Id }
 };
 
-use crate::Result;
+use crate::{
+	syncing::bulk::resume_pending_job,
+	Result
+};
 
 fn add_roles_to_cache(guild_id: Id<GuildMarker>, roles: &Vec<Role>) {
 	for role in roles {
@@ -22,6 +25,8 @@ pub fn guild_create(guild_create: GuildCreate) -> Result<()> {
 
 			CACHE.discord.guilds.insert(guild_id, guild.into());
 			tracing::info!("model.discord.guild.create (guild_id={guild_id})");
+
+			resume_pending_job(guild_id);
 		},
 		GuildCreate::Unavailable(guild) => if ! guild.unavailable {
 			tokio::spawn(async move {
@@ -37,6 +42,8 @@ pub fn guild_create(guild_create: GuildCreate) -> Result<()> {
 
 				CACHE.discord.guilds.insert(guild_id, guild.into());
 				tracing::info!("model.discord.guild.create (guild_id={guild_id})");
+
+				resume_pending_job(guild_id);
 			});
 		}
 	}
@@ -12,6 +12,10 @@ use twilight_model::{
 };
 
 use crate::{
+	syncing::{
+		automatic::{ cancel_unlinked_kick, sync_new_member },
+		enforcement::enforce_member_roles
+	},
 	visual_scripting::{ process_document, variable_from_member },
 	Result, Context,
 	PENDING_VERIFICATION_TIMER
@@ -23,6 +27,7 @@ pub async fn member_add(member_add: MemberAdd) -> Result<()> {
 	let user_id = member_add.user.id;
 	let guild_id = member_add.guild_id;
 	let is_pending = member_add.pending;
+	let is_bot = member_add.user.bot;
 	CACHE
 		.discord
 		.members
@@ -45,6 +50,12 @@ pub async fn member_add(member_add: MemberAdd) -> Result<()> {
 		}
 	}
 
+	// members going through membership screening are synced once they're done instead.
+	if !is_bot && !is_pending {
+		sync_new_member(guild_id, user_id)
+			.await?;
+	}
+
 	Ok(())
 }
 
@@ -65,12 +76,14 @@ pub async fn member_chunk(context: Context, member_chunk: MemberChunk) -> Result
 pub async fn member_update(member_update: MemberUpdate) -> Result<()> {
 	let user_id = member_update.user.id;
 	let guild_id = member_update.guild_id;
+	let mut is_screening_completed = false;
 	if !member_update.pending {
 		let key = (guild_id, user_id);
 		let pending = &PENDING_MEMBERS;
 		let mut pending = pending.write().await;
 		if pending.contains(&key) {
 			pending.retain(|x| *x != key);
+			is_screening_completed = true;
 			
 			if member_update.roles.is_empty() {
 				let onboarding = CACHE.discord.guild_onboarding(guild_id).await?;
@@ -80,7 +93,9 @@ pub async fn member_update(member_update: MemberUpdate) -> Result<()> {
 						PENDING_VERIFICATION_TIMER.write().await.push((guild_id, user_id, SystemTime::now()));
 						tracing::info!("added {} to PENDING_VERIFICATION_TIMER", user_id);
 
-						return Ok(());
+						// the member has no roles at this point, so the cached member is still accurate enough to sync.
+						drop(pending);
+						return sync_new_member(guild_id, user_id).await;
 					}
 				}
 			}
@@ -115,10 +130,21 @@ pub async fn member_update(member_update: MemberUpdate) -> Result<()> {
 		}
 	}
 
+	let mut old_roles = None;
 	if let Some(mut member) = CACHE.discord.members.get_mut(&(member_update.guild_id, member_update.user.id)) {
+		old_roles = Some(member.roles.clone());
 		member.update(&member_update);
 	}
 
+	// onboarding may have assigned roles, so this waits for the cached member to be up to date.
+	if is_screening_completed {
+		sync_new_member(guild_id, user_id)
+			.await?;
+	} else if let Some(old_roles) = old_roles && !member_update.user.bot {
+		enforce_member_roles(guild_id, user_id, &old_roles, &member_update.roles)
+			.await?;
+	}
+
 	Ok(())
 }
 
@@ -126,6 +152,7 @@ pub async fn member_remove(member_remove: MemberRemove) -> Result<()> {
 	let user_id = member_remove.user.id;
 	let guild_id = member_remove.guild_id;
 	CACHE.discord.members.remove(&(guild_id, user_id));
+	cancel_unlinked_kick(guild_id, user_id);
 	tracing::info!("model.discord.member.delete (guild_id={guild_id}) (user_id={user_id})");
 
 	Ok(())
@@ -6,6 +6,12 @@ pub enum Error {
 	#[error("Fetch: {0} {1}")]
 	Fetch(String, String),
 
+	#[error("GitHub GraphQL Error: {0}")]
+	GitHubGraphQL(String),
+
+	#[error("There is no GitHub API token configured")]
+	GitHubTokenMissing,
+
 	#[error("Model: {0}")]
 	Model(#[from] mellow_models::Error),
 
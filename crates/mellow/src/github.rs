@@ -0,0 +1,410 @@
+use serde::{ de::DeserializeOwned, Serialize, Deserialize };
+use reqwest::{ header, Client, StatusCode };
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use serde_json::{ Map, Value };
+use std::{
+	collections::HashMap,
+	time::{ Duration, Instant }
+};
+
+use crate::{ Error, Result };
+
+static API_TOKEN: Lazy<Option<String>> = Lazy::new(||
+	std::env::var("GITHUB_API_TOKEN").ok()
+);
+
+static CLIENT: Lazy<Client> = Lazy::new(||
+	Client::builder()
+		.default_headers({
+			let mut headers = header::HeaderMap::new();
+			headers.append(header::ACCEPT, "application/vnd.github+json".parse().unwrap());
+			headers.append("x-github-api-version", "2022-11-28".parse().unwrap());
+			if let Some(token) = &*API_TOKEN {
+				headers.append(header::AUTHORIZATION, format!("Bearer {token}").parse().unwrap());
+			}
+			headers
+		})
+		.user_agent("mellow")
+		.build()
+		.unwrap()
+);
+
+// this can be pointed at a local stand-in when testing.
+static API_URL: Lazy<String> = Lazy::new(||
+	std::env::var("GITHUB_API_URL").unwrap_or("https://api.github.com".into())
+);
+
+static CACHE_TTL: Lazy<Duration> = Lazy::new(||
+	Duration::from_secs(std::env::var("GITHUB_CACHE_TTL")
+		.ok()
+		.and_then(|x| x.parse().ok())
+		.unwrap_or(300)
+	)
+);
+
+// keyed by owner and repository name in lowercase, as github doesn't care about their case either.
+static CONTRIBUTORS: Lazy<DashMap<(String, String), (Instant, Vec<Contributor>)>> = Lazy::new(DashMap::new);
+
+const MAX_PAGE_SIZE: usize = 100;
+
+// how many users are looked up within a single graphql query.
+const GRAPHQL_BATCH_SIZE: usize = 50;
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct Contributor {
+	pub id: u64,
+	pub login: String
+}
+
+/// Every contributor of the given repository, which takes a request per hundred of them.
+/// Results are cached per repository for `GITHUB_CACHE_TTL` seconds.
+pub async fn get_repository_contributors(owner: &str, repository: &str) -> Result<Vec<Contributor>> {
+	let key = (owner.to_lowercase(), repository.to_lowercase());
+	if let Some(cached) = CONTRIBUTORS.get(&key) && cached.0.elapsed() < *CACHE_TTL {
+		return Ok(cached.1.clone());
+	}
+
+	let contributors = fetch_repository_contributors(owner, repository)
+		.await?;
+	CONTRIBUTORS.insert(key, (Instant::now(), contributors.clone()));
+
+	Ok(contributors)
+}
+
+async fn fetch_repository_contributors(owner: &str, repository: &str) -> Result<Vec<Contributor>> {
+	let mut contributors: Vec<Contributor> = vec![];
+	for page in 1.. {
+		let response = CLIENT
+			.get(format!("{}/repos/{owner}/{repository}/contributors", *API_URL))
+			.query(&[("per_page", MAX_PAGE_SIZE), ("page", page)])
+			.send()
+			.await?
+			.error_for_status()?;
+		// empty repositories respond without a body.
+		if response.status() == StatusCode::NO_CONTENT {
+			break;
+		}
+
+		let results: Vec<Contributor> = response
+			.json()
+			.await?;
+		let is_last_page = results.len() < MAX_PAGE_SIZE;
+		contributors.extend(results);
+
+		if is_last_page {
+			break;
+		}
+	}
+
+	Ok(contributors)
+}
+
+#[derive(Serialize)]
+struct GraphQLRequest {
+	query: String,
+	variables: Map<String, Value>
+}
+
+#[derive(Deserialize)]
+struct GraphQLResponse<T> {
+	data: Option<T>,
+	#[serde(default)]
+	errors: Vec<GraphQLError>
+}
+
+#[derive(Deserialize)]
+struct GraphQLError {
+	#[serde(rename = "type")]
+	kind: Option<String>,
+	message: String
+}
+
+// github responds with partial data and errors alongside it, accounts that don't exist are reported as errors too.
+async fn graphql<T: DeserializeOwned>(query: String, variables: Map<String, Value>) -> Result<T> {
+	if API_TOKEN.is_none() {
+		return Err(Error::GitHubTokenMissing);
+	}
+
+	let response: GraphQLResponse<T> = CLIENT
+		.post(format!("{}/graphql", *API_URL))
+		.json(&GraphQLRequest {
+			query,
+			variables
+		})
+		.send()
+		.await?
+		.error_for_status()?
+		.json()
+		.await?;
+	let errors: Vec<String> = response.errors
+		.into_iter()
+		.filter(|x| x.kind.as_deref() != Some("NOT_FOUND"))
+		.map(|x| x.message)
+		.collect();
+	match response.data {
+		Some(data) if errors.is_empty() => Ok(data),
+		_ => Err(Error::GitHubGraphQL(errors.join(", ")))
+	}
+}
+
+// every username is passed as its own variable and queried under its own alias, `u0`, `u1`, and so on.
+fn username_variables(usernames: &[String]) -> (String, Map<String, Value>) {
+	let declarations = (0..usernames.len())
+		.map(|x| format!(", $u{x}: String!"))
+		.collect();
+	let variables = usernames
+		.iter()
+		.enumerate()
+		.map(|(index, username)| (format!("u{index}"), Value::String(username.clone())))
+		.collect();
+	(declarations, variables)
+}
+
+#[derive(Deserialize)]
+struct OrganizationMember {
+	organization: Option<Value>
+}
+
+/// Which of the given users are public members of the given organisation, requires `GITHUB_API_TOKEN`.
+/// Private memberships can't be seen without the user's own authorisation.
+pub async fn get_public_organization_members(organization: &str, usernames: &[String]) -> Result<Vec<String>> {
+	let mut members: Vec<String> = vec![];
+	for chunk in usernames.chunks(GRAPHQL_BATCH_SIZE) {
+		let (declarations, mut variables) = username_variables(chunk);
+		variables.insert("organization".into(), Value::String(organization.to_string()));
+
+		let selections: String = (0..chunk.len())
+			.map(|x| format!("u{x}: user(login: $u{x}) {{ organization(login: $organization) {{ id }} }}\n"))
+			.collect();
+		let data: HashMap<String, Option<OrganizationMember>> = graphql(format!("query($organization: String!{declarations}) {{\n{selections}}}"), variables)
+			.await?;
+		for (index, username) in chunk.iter().enumerate() {
+			if data.get(&format!("u{index}")).and_then(|x| x.as_ref()).is_some_and(|x| x.organization.is_some()) {
+				members.push(username.clone());
+			}
+		}
+	}
+
+	Ok(members)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SponsorshipData {
+	repository_owner: Option<HashMap<String, bool>>
+}
+
+/// Which of the given users currently sponsor `account`, requires `GITHUB_API_TOKEN`.
+pub async fn get_sponsors(account: &str, usernames: &[String]) -> Result<Vec<String>> {
+	let mut sponsors: Vec<String> = vec![];
+	for chunk in usernames.chunks(GRAPHQL_BATCH_SIZE) {
+		let (declarations, mut variables) = username_variables(chunk);
+		variables.insert("account".into(), Value::String(account.to_string()));
+
+		let selections: String = (0..chunk.len())
+			.map(|x| format!("u{x}: isSponsoredBy(accountLogin: $u{x})\n"))
+			.collect();
+		let data: SponsorshipData = graphql(format!("query($account: String!{declarations}) {{\nrepositoryOwner(login: $account) {{\n... on Sponsorable {{\n{selections}}}\n}}\n}}"), variables)
+			.await?;
+		// accounts that can't be sponsored come back without any of the fields.
+		let Some(owner) = data.repository_owner else {
+			continue;
+		};
+		for (index, username) in chunk.iter().enumerate() {
+			if owner.get(&format!("u{index}")).copied().unwrap_or_default() {
+				sponsors.push(username.clone());
+			}
+		}
+	}
+
+	Ok(sponsors)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+	use once_cell::sync::Lazy;
+	use serde_json::{ json, Value };
+	use std::{
+		io::{ BufRead, BufReader, Read, Write },
+		net::TcpListener,
+		sync::atomic::{ AtomicUsize, Ordering }
+	};
+
+	use super::{ get_public_organization_members, get_repository_contributors, get_sponsors };
+
+	// only requests for repositories owned by "counted", so other tests don't get in the way.
+	static CONTRIBUTOR_REQUESTS: AtomicUsize = AtomicUsize::new(0);
+
+	// a minimal stand-in for the github api, see `respond` for how it answers.
+	// any username or repository starting with "broken" makes it respond with a server error.
+	pub(crate) static STAND_IN: Lazy<()> = Lazy::new(|| {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		// SAFETY: set once, before any of these are read, the only other variables tests set are the roblox stand-in's own.
+		unsafe {
+			std::env::set_var("GITHUB_API_URL", format!("http://{}", listener.local_addr().unwrap()));
+			std::env::set_var("GITHUB_API_TOKEN", "stand-in");
+		}
+
+		std::thread::spawn(move || {
+			for stream in listener.incoming() {
+				let Ok(mut stream) = stream else {
+					continue;
+				};
+				let mut request_line = String::new();
+				let mut reader = BufReader::new(&stream);
+				reader.read_line(&mut request_line).unwrap();
+				let mut content_length = 0;
+				let mut line = String::new();
+				while reader.read_line(&mut line).unwrap() > 2 {
+					if let Some((name, value)) = line.split_once(':') && name.eq_ignore_ascii_case("content-length") {
+						content_length = value.trim().parse().unwrap();
+					}
+					line.clear();
+				}
+				let mut body = vec![0; content_length];
+				reader.read_exact(&mut body).unwrap();
+
+				let target = request_line
+					.split(' ')
+					.nth(1)
+					.unwrap_or_default();
+				let (path, query) = target
+					.split_once('?')
+					.unwrap_or((target, ""));
+				let (status, body) = respond(path, query, serde_json::from_slice(&body).unwrap_or_default());
+				write!(stream, "HTTP/1.1 {status}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}", body.len()).unwrap();
+			}
+		});
+	});
+
+	fn respond(path: &str, query: &str, body: Value) -> (&'static str, String) {
+		let segments: Vec<&str> = path
+			.trim_matches('/')
+			.split('/')
+			.collect();
+		match segments.as_slice() {
+			["repos", owner, repository, "contributors"] => {
+				if *owner == "counted" {
+					CONTRIBUTOR_REQUESTS.fetch_add(1, Ordering::Relaxed);
+				}
+				let page: u64 = query
+					.split('&')
+					.find_map(|x| x.strip_prefix("page="))
+					.and_then(|x| x.parse().ok())
+					.unwrap_or(1);
+				match *repository {
+					"empty" => ("204 No Content", String::new()),
+					x if x.starts_with("broken") => ("500 Internal Server Error", "{}".into()),
+					// a full first page of a hundred contributors, then one more on the second.
+					_ => {
+						let ids = match page {
+							1 => 1..=100,
+							2 => 101..=101,
+							_ => return ("400 Bad Request", "{}".into())
+						};
+						("200 OK", Value::Array(ids.map(|x| json!({ "id": x, "login": format!("user{x}") })).collect()).to_string())
+					}
+				}
+			},
+			["graphql"] => {
+				let variables = body["variables"].as_object().cloned().unwrap_or_default();
+				let usernames: Vec<(&String, &str)> = variables
+					.iter()
+					.filter(|x| x.0.starts_with('u'))
+					.map(|(alias, username)| (alias, username.as_str().unwrap_or_default()))
+					.collect();
+				if usernames.iter().any(|x| x.1.starts_with("broken")) {
+					return ("502 Bad Gateway", "{}".into());
+				}
+
+				let query = body["query"].as_str().unwrap_or_default();
+				// users starting with "ghost" don't exist, which github reports as an error next to the data.
+				let mut errors: Vec<Value> = vec![];
+				let mut data = serde_json::Map::new();
+				for (alias, username) in usernames {
+					if username.starts_with("ghost") {
+						errors.push(json!({ "type": "NOT_FOUND", "message": format!("Could not resolve to a User with the login of '{username}'.") }));
+						data.insert(alias.clone(), Value::Null);
+					} else if query.contains("isSponsoredBy") {
+						data.insert(alias.clone(), Value::Bool(username.starts_with("sponsor")));
+					} else {
+						data.insert(alias.clone(), json!({ "organization": username.starts_with("member").then(|| json!({ "id": "O_1" })) }));
+					}
+				}
+				let data = if query.contains("isSponsoredBy") {
+					json!({ "repositoryOwner": (variables["account"] != "unsponsorable").then_some(data) })
+				} else { Value::Object(data) };
+				("200 OK", json!({ "data": data, "errors": errors }).to_string())
+			},
+			_ => ("404 Not Found", "{}".into())
+		}
+	}
+
+	fn usernames(usernames: &[&str]) -> Vec<String> {
+		usernames
+			.iter()
+			.map(|x| x.to_string())
+			.collect()
+	}
+
+	#[tokio::test]
+	async fn contributors_are_paged_through_and_cached() {
+		Lazy::force(&STAND_IN);
+
+		let contributors = get_repository_contributors("counted", "repository")
+			.await
+			.unwrap();
+		assert_eq!(contributors.len(), 101);
+		assert_eq!(contributors.last().map(|x| x.id), Some(101));
+		assert_eq!(CONTRIBUTOR_REQUESTS.load(Ordering::Relaxed), 2);
+
+		// the owner and repository are case-insensitive, so this is served from the cache.
+		get_repository_contributors("Counted", "Repository")
+			.await
+			.unwrap();
+		assert_eq!(CONTRIBUTOR_REQUESTS.load(Ordering::Relaxed), 2);
+
+		assert!(get_repository_contributors("owner", "empty").await.unwrap().is_empty());
+		assert!(get_repository_contributors("owner", "broken").await.is_err());
+	}
+
+	#[tokio::test]
+	async fn only_public_organization_members_are_returned() {
+		Lazy::force(&STAND_IN);
+
+		let members = get_public_organization_members("organization", &usernames(&["member1", "outsider", "ghost", "member2"]))
+			.await
+			.unwrap();
+		assert_eq!(members, ["member1", "member2"]);
+
+		// more users than fit in a single query.
+		let many: Vec<String> = (0..120)
+			.map(|x| if x % 2 == 0 { format!("member{x}") } else { format!("outsider{x}") })
+			.collect();
+		let members = get_public_organization_members("organization", &many)
+			.await
+			.unwrap();
+		assert_eq!(members.len(), 60);
+	}
+
+	#[tokio::test]
+	async fn only_sponsors_are_returned() {
+		Lazy::force(&STAND_IN);
+
+		let sponsors = get_sponsors("account", &usernames(&["sponsor1", "freeloader", "ghost"]))
+			.await
+			.unwrap();
+		assert_eq!(sponsors, ["sponsor1"]);
+
+		assert!(get_sponsors("unsponsorable", &usernames(&["sponsor1"])).await.unwrap().is_empty());
+	}
+
+	#[tokio::test]
+	async fn server_errors_are_errors() {
+		Lazy::force(&STAND_IN);
+
+		assert!(get_public_organization_members("organization", &usernames(&["member1", "broken"])).await.is_err());
+		assert!(get_sponsors("account", &usernames(&["broken"])).await.is_err());
+	}
+}
\ No newline at end of file
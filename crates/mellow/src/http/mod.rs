@@ -34,6 +34,9 @@ pub enum ApiError {
 	#[display(fmt = "invalid_api_key")]
 	InvalidApiKey,
 
+	#[display(fmt = "sync_not_found")]
+	SyncNotFound,
+
 	#[display(fmt = "user_not_found")]
 	UserNotFound,
 
@@ -57,6 +60,7 @@ impl actix_web::error::ResponseError for ApiError {
 			ApiError::Unknown(_) => StatusCode::INTERNAL_SERVER_ERROR,
 			ApiError::GenericInvalidRequest => StatusCode::BAD_REQUEST,
 			ApiError::InvalidApiKey => StatusCode::FORBIDDEN,
+			ApiError::SyncNotFound |
 			ApiError::UserNotFound => StatusCode::NOT_FOUND
 		}
 	}
@@ -2,6 +2,7 @@ use actix_web::{
 	Responder, HttpRequest, HttpResponse,
 	get, web, post
 };
+use chrono::{ DateTime, Utc };
 use hmac::{ Mac, Hmac };
 use mellow_cache::CACHE;
 use mellow_models::{
@@ -9,16 +10,18 @@ use mellow_models::{
 		user::connection::ConnectionModel,
 		DocumentModel
 	},
-	mellow::server::{ ServerModel, UserSettingsModel }
+	mellow::server::{ ServerModel, SyncHistoryModel, UserSettingsModel },
+	patreon::user_identity::MemberAttributes
 };
 use mellow_util::{
 	hakuid::{
-		marker::{ ConnectionMarker, DocumentMarker, UserMarker as HakuUserMarker },
+		marker::{ ConnectionMarker, DocumentMarker, SyncHistoryMarker, SyncJobMarker, UserMarker as HakuUserMarker },
 		HakuId
 	},
 	DISCORD_INTERACTION_CLIENT
 };
 use serde::Deserialize;
+use serde_json::Value;
 use sha2::Sha256;
 use twilight_util::builder::command::CommandBuilder;
 use twilight_model::{
@@ -40,11 +43,15 @@ use crate::{
 		logging::{ ServerLog, send_logs }
 	},
 	syncing::{
+		automatic::sync_connection_servers,
+		bulk,
+		rank_binds::RankBindPlan,
+		rollback::{ RollbackResult, rollback_sync, rollback_sync_job },
 		ConnectionMetadata, PatreonPledge, SyncingInitiator, SyncMemberResult,
 		sync_single_user
 	},
 	util::user_server_connections,
-	Result
+	Error, Result
 };
 
 type HmacSha256 = Hmac<Sha256>;
@@ -57,6 +64,12 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
     cfg
 		.service(index)
 		.service(sync_member)
+		.service(preview_member_sync)
+		.service(member_sync_history)
+		.service(server_sync_history)
+		.service(rollback_member_sync)
+		.service(rollback_server_sync_job)
+		.service(roblox_rank_binds)
 		.service(update_discord_commands)
 		.service(patreon_webhook)
 		.service(
@@ -76,7 +89,10 @@ async fn index() -> impl Responder {
 
 #[derive(Deserialize)]
 struct SyncMemberPayload {
-	webhook_token: Option<String>
+	webhook_token: Option<String>,
+	/// Includes how every action was evaluated in the result, see [`crate::syncing::SyncTrace`].
+	#[serde(default)]
+	explain: bool
 }
 
 #[post("/server/{server_id}/member/{member_id}/sync")]
@@ -88,15 +104,131 @@ async fn sync_member(request: HttpRequest, body: web::Json<SyncMemberPayload>, p
 		let member_id: Id<UserMarker> = Id::new(member_id);
 		if let Some(user_id) = CACHE.hakumi.user_by_discord(guild_id, member_id).await? {
 			return Ok(web::Json(if let Some(token) = &body.webhook_token {
-				sync_with_token(guild_id, user_id, member_id, token, false, None).await?
+				sync_with_token(guild_id, user_id, member_id, token, false, None, false, body.explain).await?
 			} else {
-				sync_single_user(guild_id, user_id, member_id, SyncingInitiator::Automatic, None).await?
+				sync_single_user(guild_id, user_id, member_id, SyncingInitiator::Automatic, None, false, body.explain).await?
 			}));
 		}
 		Err(ApiError::UserNotFound)
 	} else { Err(ApiError::InvalidApiKey) }
 }
 
+// unlike a regular sync, the preview also includes whether the member would be banned or kicked.
+#[post("/server/{server_id}/member/{member_id}/sync/preview")]
+async fn preview_member_sync(request: HttpRequest, path: web::Path<(u64, u64)>) -> ApiResult<web::Json<Value>> {
+	if request.headers().get("x-api-key").map_or(false, |x| x.to_str().unwrap() == API_KEY) {
+		let (guild_id, member_id) = path.into_inner();
+		let guild_id: Id<GuildMarker> = Id::new(guild_id);
+		let member_id: Id<UserMarker> = Id::new(member_id);
+		if let Some(user_id) = CACHE.hakumi.user_by_discord(guild_id, member_id).await? {
+			let result = sync_single_user(guild_id, user_id, member_id, SyncingInitiator::Automatic, None, true, false)
+				.await?;
+			return Ok(web::Json(
+				serde_json::to_value(result.details()).map_err(Error::from)?
+			));
+		}
+		Err(ApiError::UserNotFound)
+	} else { Err(ApiError::InvalidApiKey) }
+}
+
+const DEFAULT_SYNC_HISTORY_LIMIT: u32 = 25;
+
+// pages are continued from the `created_at` and `id` of the last entry on the previous page, both are needed.
+#[derive(Deserialize)]
+struct SyncHistoryQuery {
+	before: Option<DateTime<Utc>>,
+	before_id: Option<HakuId<SyncHistoryMarker>>,
+	limit: Option<u32>
+}
+
+#[get("/server/{server_id}/member/{member_id}/sync_history")]
+async fn member_sync_history(request: HttpRequest, query: web::Query<SyncHistoryQuery>, path: web::Path<(u64, u64)>) -> ApiResult<web::Json<Vec<SyncHistoryModel>>> {
+	if request.headers().get("x-api-key").map_or(false, |x| x.to_str().unwrap() == API_KEY) {
+		let (guild_id, member_id) = path.into_inner();
+		Ok(web::Json(
+			SyncHistoryModel::get_member_page(Id::new(guild_id), Id::new(member_id), query.before.zip(query.before_id), query.limit.unwrap_or(DEFAULT_SYNC_HISTORY_LIMIT)).await?
+		))
+	} else { Err(ApiError::InvalidApiKey) }
+}
+
+#[get("/server/{server_id}/sync_history")]
+async fn server_sync_history(request: HttpRequest, query: web::Query<SyncHistoryQuery>, path: web::Path<u64>) -> ApiResult<web::Json<Vec<SyncHistoryModel>>> {
+	if request.headers().get("x-api-key").map_or(false, |x| x.to_str().unwrap() == API_KEY) {
+		Ok(web::Json(
+			SyncHistoryModel::get_server_page(Id::new(path.into_inner()), query.before.zip(query.before_id), query.limit.unwrap_or(DEFAULT_SYNC_HISTORY_LIMIT)).await?
+		))
+	} else { Err(ApiError::InvalidApiKey) }
+}
+
+#[derive(Deserialize)]
+struct RollbackPayload {
+	initiator_id: Option<Id<UserMarker>>
+}
+
+#[post("/server/{server_id}/sync_history/{sync_id}/rollback")]
+async fn rollback_member_sync(request: HttpRequest, body: web::Json<RollbackPayload>, path: web::Path<(u64, HakuId<SyncHistoryMarker>)>) -> ApiResult<web::Json<RollbackResult>> {
+	if request.headers().get("x-api-key").map_or(false, |x| x.to_str().unwrap() == API_KEY) {
+		let (guild_id, sync_id) = path.into_inner();
+		let guild_id: Id<GuildMarker> = Id::new(guild_id);
+		let mut entry = SyncHistoryModel::get(guild_id, sync_id)
+			.await?
+			.filter(|x| x.rolled_back_at.is_none())
+			.ok_or(ApiError::SyncNotFound)?;
+		let result = rollback_sync(guild_id, &mut entry)
+			.await?;
+		if let Some(log) = result.create_log(body.initiator_id) {
+			send_logs(guild_id, vec![log])
+				.await?;
+		}
+
+		Ok(web::Json(result))
+	} else { Err(ApiError::InvalidApiKey) }
+}
+
+#[post("/server/{server_id}/sync_job/{job_id}/rollback")]
+async fn rollback_server_sync_job(request: HttpRequest, body: web::Json<RollbackPayload>, path: web::Path<(u64, HakuId<SyncJobMarker>)>) -> ApiResult<web::Json<Vec<RollbackResult>>> {
+	if request.headers().get("x-api-key").map_or(false, |x| x.to_str().unwrap() == API_KEY) {
+		let (guild_id, job_id) = path.into_inner();
+		let guild_id: Id<GuildMarker> = Id::new(guild_id);
+		if bulk::is_job_running(guild_id) {
+			return Err(ApiError::GenericInvalidRequest);
+		}
+
+		let (results, _) = rollback_sync_job(guild_id, job_id)
+			.await?;
+		send_logs(guild_id, results
+			.iter()
+			.filter_map(|x| x.create_log(body.initiator_id))
+			.collect()
+		).await?;
+
+		Ok(web::Json(results))
+	} else { Err(ApiError::InvalidApiKey) }
+}
+
+#[derive(Deserialize)]
+struct RankBindsPayload {
+	#[serde(default)]
+	apply: bool
+}
+
+/// Returns the rank binds for a Roblox group, which are only created when `apply` is set.
+#[post("/server/{server_id}/roblox_group/{group_id}/rank_binds")]
+async fn roblox_rank_binds(request: HttpRequest, body: web::Json<RankBindsPayload>, path: web::Path<(u64, u64)>) -> ApiResult<web::Json<RankBindPlan>> {
+	if request.headers().get("x-api-key").map_or(false, |x| x.to_str().unwrap() == API_KEY) {
+		let (guild_id, group_id) = path.into_inner();
+		let guild_id: Id<GuildMarker> = Id::new(guild_id);
+		let mut plan = RankBindPlan::generate(guild_id, group_id)
+			.await?;
+		if body.apply {
+			plan.apply(guild_id)
+				.await?;
+		}
+
+		Ok(web::Json(plan))
+	} else { Err(ApiError::InvalidApiKey) }
+}
+
 #[post("/supabase_webhooks/action_log")]
 async fn action_log_webhook(request: HttpRequest, payload: web::Payload) -> ApiResult<HttpResponse> {
 	let mut body = payload.to_bytes().await.unwrap().to_vec();
@@ -195,27 +327,49 @@ async fn internal_model_event(_request: HttpRequest, payload: web::Json<ModelEve
 					.remove(&guild_id);
 			}
 		},
-		ModelKind::UserConnection(user_id, connection_id) => match model_update.kind {
-			ModelEventKind::Created => if let Some(connection_ids) = CACHE.hakumi.user_connections.get_mut(&user_id) {
-				connection_ids.insert(connection_id);
-			},
-			ModelEventKind::Updated => if CACHE.hakumi.connections.contains_key(&connection_id) {
-				let new_model = ConnectionModel::get(connection_id)
-					.await?
-					.unwrap();
-				CACHE
-					.hakumi
-					.connections
-					.insert(connection_id, new_model);
-			},
-			ModelEventKind::Deleted => {
-				for connection_ids in CACHE.hakumi.user_connections.iter_mut() {
-					connection_ids.remove(&connection_id);
+		ModelKind::UserConnection(user_id, connection_id) => {
+			// new connections aren't shared with any server yet, sharing them is a user settings event instead.
+			let affects_criteria = match model_update.kind {
+				ModelEventKind::Created => {
+					if let Some(connection_ids) = CACHE.hakumi.user_connections.get_mut(&user_id) {
+						connection_ids.insert(connection_id);
+					}
+					false
+				},
+				ModelEventKind::Updated => if CACHE.hakumi.connections.contains_key(&connection_id) {
+					let new_model = ConnectionModel::get(connection_id)
+						.await?
+						.unwrap();
+					let affects_criteria = CACHE
+						.hakumi
+						.connections
+						.get(&connection_id)
+						.is_none_or(|x| x.affects_criteria(&new_model));
+					CACHE
+						.hakumi
+						.connections
+						.insert(connection_id, new_model);
+					affects_criteria
+				// nothing here has read the connection yet, so there's nothing to compare against.
+				} else { true },
+				ModelEventKind::Deleted => {
+					for connection_ids in CACHE.hakumi.user_connections.iter_mut() {
+						connection_ids.remove(&connection_id);
+					}
+					CACHE
+						.hakumi
+						.connections
+						.remove(&connection_id);
+					true
 				}
-				CACHE
-					.hakumi
-					.connections
-					.remove(&connection_id);
+			};
+
+			if affects_criteria {
+				tokio::spawn(async move {
+					if let Err(error) = sync_connection_servers(user_id, connection_id).await {
+						tracing::warn!(%user_id, %connection_id, %error, "failed to sync members after connection update");
+					}
+				});
 			}
 		},
 		ModelKind::UserSettings(guild_id, user_id) => {
@@ -252,11 +406,11 @@ async fn internal_model_event(_request: HttpRequest, payload: web::Json<ModelEve
 				if let Some(connection) = connections.into_iter().find(|x| x.is_discord()) {
 					let member_id = Id::new(connection.sub.parse().unwrap());
 					if let Some((_,sign_up)) = CACHE.mellow.sign_ups.remove(&member_id) {
-						sync_with_token(guild_id, user_id, member_id, &sign_up.interaction_token, true, None)
+						sync_with_token(guild_id, user_id, member_id, &sign_up.interaction_token, true, None, false, false)
 							.await
 							.unwrap();
 					} else {
-						let result = sync_single_user(guild_id, user_id, member_id, SyncingInitiator::Automatic, None)
+						let result = sync_single_user(guild_id, user_id, member_id, SyncingInitiator::Automatic, None, false, false)
 							.await
 							.unwrap();
 						if let Some(result_log) = result.create_log() {
@@ -306,6 +460,8 @@ struct PayloadData<T> {
 
 #[derive(Deserialize)]
 struct WebhookPayload {
+	#[serde(default)]
+	attributes: MemberAttributes,
 	relationships: PayloadRelationships
 }
 
@@ -341,19 +497,22 @@ async fn patreon_webhook(payload: web::Payload, path: web::Path<Id<GuildMarker>>
 		let discord_id = Id::new(connections.into_iter().find(|x| x.is_discord()).unwrap().id.to_string().parse().map_err(|_| ApiError::GenericInvalidRequest)?);
 		sync_single_user(guild_id, user_id, discord_id, SyncingInitiator::Automatic, Some(ConnectionMetadata {
 			issues: Vec::new(),
+			github_relations: vec![],
 			patreon_pledges: vec![PatreonPledge {
 				campaign_id: payload.data.relationships.campaign.data.id.clone(),
 				connection_id: user_server_connections(guild_id, user_id)
 					.await?
 					.into_iter()
-					.find(|x| x.is_patreon())
-					.unwrap()
+					.find(|x| x.is_patreon() && x.sub == payload.data.relationships.user.data.id)
+					.ok_or(ApiError::UserNotFound)?
 					.id,
 				tiers: payload.data.relationships.currently_entitled_tiers.data.iter().map(|x| x.id.clone()).collect(),
+				attributes: payload.data.attributes.clone(),
 				user_id: user_id.value
 			}],
-			roblox_memberships: vec![]
-		})).await?;
+			roblox_memberships: vec![],
+			..Default::default()
+		}), false, false).await?;
 	}
 
 	Ok(HttpResponse::Ok().finish())
@@ -382,6 +541,11 @@ fn app_command(command: &crate::Command, kind: CommandType) -> Result<Command> {
 	if let Some(permissions) = command.default_member_permissions()? {
 		builder = builder.default_member_permissions(permissions);
 	}
+	if kind == CommandType::ChatInput && let Some(options) = command.options {
+		for option in options() {
+			builder = builder.option(option);
+		}
+	}
 	Ok(builder.build())
 }
 
@@ -16,13 +16,17 @@ use twilight_model::{
 	http::interaction::{ InteractionResponse, InteractionResponseData, InteractionResponseType },
 	guild::Permissions,
 	channel::{ message::MessageFlags, Channel, Message },
-	application::interaction::{ Interaction as TwilightInteraction, InteractionData, InteractionType }
+	application::interaction::{
+		application_command::CommandOptionValue,
+		Interaction as TwilightInteraction, InteractionData, InteractionType
+	}
 };
 
 use crate::{
 	commands::COMMANDS,
 	visual_scripting::{ process_document, variable_from_member },
-	Result, Context, CommandResponse
+	Result, Context, CommandResponse,
+	cast
 };
 
 #[derive(Clone, Debug, PartialEq)]
@@ -57,6 +61,33 @@ impl Interaction {
 			)
 		} else { None })
 	}
+
+	pub fn boolean_option(&self, name: &str) -> Option<bool> {
+		if let Some(InteractionData::ApplicationCommand(data)) = &self.data {
+			data.options
+				.iter()
+				.find(|x| x.name == name)
+				.and_then(|x| cast!(&x.value, CommandOptionValue::Boolean).copied())
+		} else { None }
+	}
+
+	pub fn integer_option(&self, name: &str) -> Option<i64> {
+		if let Some(InteractionData::ApplicationCommand(data)) = &self.data {
+			data.options
+				.iter()
+				.find(|x| x.name == name)
+				.and_then(|x| cast!(&x.value, CommandOptionValue::Integer).copied())
+		} else { None }
+	}
+
+	pub fn user_option(&self, name: &str) -> Option<Id<UserMarker>> {
+		if let Some(InteractionData::ApplicationCommand(data)) = &self.data {
+			data.options
+				.iter()
+				.find(|x| x.name == name)
+				.and_then(|x| cast!(&x.value, CommandOptionValue::User).copied())
+		} else { None }
+	}
 }
 
 #[derive(Deserialize_repr, Debug)]
@@ -1,10 +1,14 @@
 #![feature(let_chains, try_blocks, duration_constructors)]
 use mellow_cache::CACHE;
-use mellow_models::hakumi::visual_scripting::{ DocumentKind, Variable };
+use mellow_models::{
+	hakumi::visual_scripting::{ DocumentKind, Variable },
+	mellow::server::SyncHistoryModel
+};
 use mellow_util::DISCORD_INTERACTION_CLIENT;
 use std::{
+	collections::HashMap,
 	sync::Arc,
-	time::{ Duration, SystemTime }
+	time::{ Duration, Instant, SystemTime }
 };
 use tokio::sync::RwLock;
 use tokio_util::sync::CancellationToken;
@@ -16,6 +20,7 @@ use twilight_model::{
 		marker::{ UserMarker, GuildMarker },
 		Id
 	},
+	application::command::CommandOption,
 	guild::Permissions,
 	channel::message::MessageFlags
 };
@@ -27,6 +32,7 @@ use visual_scripting::{ process_document, variable_from_member };
 mod commands;
 mod discord;
 mod error;
+mod github;
 mod http;
 mod interaction;
 mod util;
@@ -44,6 +50,7 @@ pub struct Command {
 	is_user: bool,
 	is_slash: bool,
 	is_message: bool,
+	options: Option<fn() -> Vec<CommandOption>>,
 	description: Option<String>,
 	default_member_permissions: Option<String>
 }
@@ -113,6 +120,13 @@ async fn main() -> std::io::Result<()> {
 
 	let job_cancel = CancellationToken::new();
 	tokio::spawn(spawn_onboarding_job(job_cancel.clone()));
+	tokio::spawn(spawn_resync_job(job_cancel.clone()));
+	tokio::spawn(spawn_history_cleanup_job(job_cancel.clone()));
+	tokio::spawn(spawn_unlinked_kick_job(job_cancel.clone()));
+
+	if let Err(error) = syncing::bulk::load_unfinished_jobs().await {
+		tracing::error!(%error, "failed to load unfinished sync jobs");
+	}
 
 	http::initialise().await?;
 	discord::gateway::initialise().await;
@@ -165,6 +179,99 @@ async fn spawn_onboarding_job(stop_signal: CancellationToken) {
 	}
 }
 
+async fn spawn_resync_job(stop_signal: CancellationToken) {
+	let mut next_resyncs: HashMap<Id<GuildMarker>, Instant> = HashMap::new();
+	loop {
+		// each server starts at a random point within its interval, so they don't all resync at once after a restart.
+		let now = Instant::now();
+		let mut due_server: Option<(Id<GuildMarker>, Duration)> = None;
+		for server in CACHE.mellow.servers.iter() {
+			let Some(interval) = server.resync_interval() else {
+				next_resyncs.remove(&server.id);
+				continue;
+			};
+			let next_resync = *next_resyncs
+				.entry(server.id)
+				.or_insert_with(|| now + interval.mul_f64(rand::random()));
+			if due_server.is_none() && next_resync <= now {
+				due_server = Some((server.id, interval));
+			}
+		}
+
+		// only one server is resynced at a time, anything else that is due waits for the next round.
+		if let Some((guild_id, interval)) = due_server {
+			next_resyncs.insert(guild_id, now + interval);
+			info!("resyncing every member of {guild_id}");
+
+			tokio::select! {
+				result = syncing::bulk::resync_server(guild_id) => if let Err(error) = result {
+					tracing::error!(%guild_id, %error, "failed to resync server");
+				},
+				_ = stop_signal.cancelled() => {
+					info!("gracefully shutting down resync job");
+					break;
+				}
+			}
+		}
+
+		tokio::select! {
+			_ = tokio::time::sleep(Duration::from_secs(60)) => {
+				continue;
+			}
+
+			_ = stop_signal.cancelled() => {
+				info!("gracefully shutting down resync job");
+				break;
+			}
+		};
+	}
+}
+
+async fn spawn_history_cleanup_job(stop_signal: CancellationToken) {
+	loop {
+		match SyncHistoryModel::delete_expired().await {
+			Ok(total_deleted) => if total_deleted > 0 {
+				info!("deleted {total_deleted} expired sync history entries");
+			},
+			Err(error) => tracing::error!(%error, "failed to delete expired sync history")
+		}
+
+		tokio::select! {
+			_ = tokio::time::sleep(Duration::from_secs(60 * 60)) => {
+				continue;
+			}
+
+			_ = stop_signal.cancelled() => {
+				info!("gracefully shutting down history cleanup job");
+				break;
+			}
+		};
+	}
+}
+
+async fn spawn_unlinked_kick_job(stop_signal: CancellationToken) {
+	loop {
+		tokio::select! {
+			_ = syncing::automatic::sync_overdue_unlinked_members() => (),
+			_ = stop_signal.cancelled() => {
+				info!("gracefully shutting down unlinked kick job");
+				break;
+			}
+		}
+
+		tokio::select! {
+			_ = tokio::time::sleep(Duration::from_secs(60)) => {
+				continue;
+			}
+
+			_ = stop_signal.cancelled() => {
+				info!("gracefully shutting down unlinked kick job");
+				break;
+			}
+		};
+	}
+}
+
 pub type BoxFuture<'a, T> = std::pin::Pin<Box<dyn std::future::Future<Output = T> + Send + 'a>>;
 
 pub use error::Result;
@@ -0,0 +1,127 @@
+use once_cell::sync::Lazy;
+use rand::Rng;
+use reqwest::{
+	header::{ self, HeaderMap },
+	Client, StatusCode
+};
+use serde::de::DeserializeOwned;
+use std::time::{ Duration, Instant };
+use tokio::sync::Mutex;
+
+use crate::Result;
+
+const MAX_ATTEMPTS: u32 = 5;
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+pub static CLIENT: Lazy<RobloxClient> = Lazy::new(RobloxClient::new);
+
+/// Wraps requests to Roblox with retries, following Roblox's rate limit headers where possible,
+/// and falling back to exponential backoff where not.
+pub struct RobloxClient {
+	http: Client,
+	// set once Roblox reports that the current rate limit window has been used up.
+	blocked_until: Mutex<Option<Instant>>
+}
+
+impl RobloxClient {
+	fn new() -> Self {
+		Self {
+			http: Client::builder()
+				.default_headers({
+					let mut headers = header::HeaderMap::new();
+					headers.append("x-api-key", env!("ROBLOX_OPEN_CLOUD_KEY").parse().unwrap());
+					headers
+				})
+				.build()
+				.unwrap(),
+			blocked_until: Mutex::new(None)
+		}
+	}
+
+	pub async fn get_json<T: DeserializeOwned>(&self, url: impl AsRef<str>, query: &[(&str, &str)]) -> Result<T> {
+		let mut attempt = 0;
+		loop {
+			self.wait_for_rate_limit().await;
+
+			attempt += 1;
+			let response = match self.http.get(url.as_ref()).query(query).send().await {
+				Ok(x) => x,
+				Err(error) => if attempt < MAX_ATTEMPTS && (error.is_connect() || error.is_timeout()) {
+					tokio::time::sleep(backoff(attempt)).await;
+					continue;
+				} else {
+					return Err(error.into());
+				}
+			};
+
+			let status = response.status();
+			let headers = response.headers();
+			if status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+				if attempt < MAX_ATTEMPTS {
+					let delay = retry_after(headers)
+						.or_else(|| rate_limit_reset(headers))
+						.unwrap_or_else(|| backoff(attempt))
+						.min(MAX_BACKOFF);
+					tracing::warn!(%status, ?delay, attempt, "roblox request failed, retrying");
+
+					tokio::time::sleep(delay).await;
+					continue;
+				}
+			} else if rate_limit_remaining(headers) == Some(0) && let Some(reset) = rate_limit_reset(headers) {
+				*self.blocked_until.lock().await = Some(Instant::now() + reset.min(MAX_BACKOFF));
+			}
+
+			return Ok(response
+				.error_for_status()?
+				.json()
+				.await?
+			);
+		}
+	}
+
+	async fn wait_for_rate_limit(&self) {
+		let blocked_until = *self.blocked_until.lock().await;
+		if let Some(blocked_until) = blocked_until {
+			let now = Instant::now();
+			if blocked_until > now {
+				tokio::time::sleep(blocked_until - now).await;
+			}
+		}
+	}
+}
+
+fn header_seconds(headers: &HeaderMap, name: &str) -> Option<Duration> {
+	headers
+		.get(name)?
+		.to_str()
+		.ok()?
+		.parse::<f64>()
+		.ok()
+		.filter(|x| x.is_finite() && *x >= 0.0)
+		.map(Duration::from_secs_f64)
+}
+
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+	header_seconds(headers, "retry-after")
+}
+
+fn rate_limit_reset(headers: &HeaderMap) -> Option<Duration> {
+	header_seconds(headers, "x-ratelimit-reset")
+}
+
+fn rate_limit_remaining(headers: &HeaderMap) -> Option<u64> {
+	headers
+		.get("x-ratelimit-remaining")?
+		.to_str()
+		.ok()?
+		.parse()
+		.ok()
+}
+
+fn backoff(attempt: u32) -> Duration {
+	let jitter = Duration::from_millis(rand::thread_rng().gen_range(0..250));
+	BASE_BACKOFF
+		.saturating_mul(2u32.saturating_pow(attempt - 1))
+		.min(MAX_BACKOFF) + jitter
+}
@@ -0,0 +1,506 @@
+use serde::Deserialize;
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use std::{
+	collections::HashMap,
+	time::{ Duration, Instant }
+};
+
+use crate::Result;
+use client::CLIENT;
+
+pub mod client;
+
+// these can be pointed at a local stand-in when testing.
+static OPEN_CLOUD_URL: Lazy<String> = Lazy::new(||
+	std::env::var("ROBLOX_OPEN_CLOUD_URL").unwrap_or("https://apis.roblox.com".into())
+);
+static GROUPS_URL: Lazy<String> = Lazy::new(||
+	std::env::var("ROBLOX_GROUPS_URL").unwrap_or("https://groups.roblox.com".into())
+);
+
+static CACHE_TTL: Lazy<Duration> = Lazy::new(||
+	Duration::from_secs(std::env::var("ROBLOX_CACHE_TTL")
+		.ok()
+		.and_then(|x| x.parse().ok())
+		.unwrap_or(300)
+	)
+);
+
+struct CachedGroupMember {
+	fetched_at: Instant,
+	member: Option<GroupMember>
+}
+
+// keyed by roblox user id and group id, users outside of a group are cached as `None`.
+static GROUP_MEMBERS: Lazy<DashMap<(String, u64), CachedGroupMember>> = Lazy::new(DashMap::new);
+
+// role id → rank, keyed by group id.
+static GROUP_RANKS: Lazy<DashMap<u64, (Instant, HashMap<u64, u8>)>> = Lazy::new(DashMap::new);
+
+const MAX_PAGE_SIZE: &str = "100";
+
+// the maximum amount of users Open Cloud accepts within a single `user in [...]` filter.
+const MEMBERSHIP_FILTER_BATCH_SIZE: usize = 50;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupMembership {
+	pub path: String,
+	pub role: String,
+	pub user: String
+}
+
+impl GroupMembership {
+	pub fn user_id(&self) -> Option<&str> {
+		self.user.strip_prefix("users/")
+	}
+
+	pub fn role_id(&self) -> Option<u64> {
+		self.role
+			.rsplit('/')
+			.next()?
+			.parse()
+			.ok()
+	}
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct GroupMembershipsResponse {
+	#[serde(default)]
+	group_memberships: Vec<GroupMembership>,
+	next_page_token: Option<String>
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupRole {
+	pub id: String,
+	pub rank: u8,
+	pub display_name: String
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct GroupRolesResponse {
+	#[serde(default)]
+	group_roles: Vec<GroupRole>,
+	next_page_token: Option<String>
+}
+
+pub async fn get_group_memberships(group_id: u64, filter: Option<&str>) -> Result<Vec<GroupMembership>> {
+	let mut memberships: Vec<GroupMembership> = vec![];
+	let mut page_token: Option<String> = None;
+	loop {
+		let mut query = vec![("maxPageSize", MAX_PAGE_SIZE)];
+		if let Some(filter) = filter {
+			query.push(("filter", filter));
+		}
+		if let Some(page_token) = &page_token {
+			query.push(("pageToken", page_token.as_str()));
+		}
+
+		let response: GroupMembershipsResponse = CLIENT
+			.get_json(format!("{}/cloud/v2/groups/{group_id}/memberships", *OPEN_CLOUD_URL), &query)
+			.await?;
+		memberships.extend(response.group_memberships);
+
+		match response.next_page_token.filter(|x| !x.is_empty()) {
+			Some(x) => page_token = Some(x),
+			None => break
+		}
+	}
+
+	Ok(memberships)
+}
+
+pub async fn get_group_roles(group_id: u64) -> Result<Vec<GroupRole>> {
+	let mut roles: Vec<GroupRole> = vec![];
+	let mut page_token: Option<String> = None;
+	loop {
+		let mut query = vec![("maxPageSize", MAX_PAGE_SIZE)];
+		if let Some(page_token) = &page_token {
+			query.push(("pageToken", page_token.as_str()));
+		}
+
+		let response: GroupRolesResponse = CLIENT
+			.get_json(format!("{}/cloud/v2/groups/{group_id}/roles", *OPEN_CLOUD_URL), &query)
+			.await?;
+		roles.extend(response.group_roles);
+
+		match response.next_page_token.filter(|x| !x.is_empty()) {
+			Some(x) => page_token = Some(x),
+			None => break
+		}
+	}
+
+	Ok(roles)
+}
+
+async fn get_group_ranks(group_id: u64) -> Result<HashMap<u64, u8>> {
+	if let Some(cached) = GROUP_RANKS.get(&group_id) && cached.0.elapsed() < *CACHE_TTL {
+		return Ok(cached.1.clone());
+	}
+
+	let ranks: HashMap<u64, u8> = get_group_roles(group_id)
+		.await?
+		.into_iter()
+		.filter_map(|x| Some((x.id.parse().ok()?, x.rank)))
+		.collect();
+	GROUP_RANKS.insert(group_id, (Instant::now(), ranks.clone()));
+
+	Ok(ranks)
+}
+
+#[derive(Clone, Debug)]
+pub struct GroupMember {
+	pub user_id: String,
+	pub group_id: u64,
+	pub role_id: u64,
+	pub rank: u8
+}
+
+#[derive(Debug, Default)]
+pub struct GroupMembers {
+	pub members: Vec<GroupMember>,
+	/// Users whose membership couldn't be looked up, they aren't necessarily outside of the groups.
+	pub failed_user_ids: Vec<String>
+}
+
+/// Fetches the membership of every given user within every given group.
+/// Results are cached per user and group for `ROBLOX_CACHE_TTL` seconds, only users without a fresh entry for every group are fetched.
+pub async fn get_group_members(group_ids: &[u64], user_ids: &[String]) -> Result<GroupMembers> {
+	if group_ids.is_empty() || user_ids.is_empty() {
+		return Ok(GroupMembers::default());
+	}
+
+	let mut members: Vec<GroupMember> = vec![];
+	let mut missing_user_ids: Vec<String> = vec![];
+	for user_id in user_ids {
+		let cached: Option<Vec<Option<GroupMember>>> = group_ids
+			.iter()
+			.map(|group_id| {
+				let cached = GROUP_MEMBERS.get(&(user_id.clone(), *group_id))?;
+				(cached.fetched_at.elapsed() < *CACHE_TTL).then(|| cached.member.clone())
+			})
+			.collect();
+		match cached {
+			Some(cached) => members.extend(cached.into_iter().flatten()),
+			None => missing_user_ids.push(user_id.clone())
+		}
+	}
+
+	if !missing_user_ids.is_empty() {
+		let fetched = fetch_group_members(group_ids, &missing_user_ids).await?;
+		let fetched_at = Instant::now();
+		// users without any membership are cached too, so they aren't looked up again until the entry expires.
+		for user_id in missing_user_ids {
+			if fetched.failed_user_ids.contains(&user_id) {
+				continue;
+			}
+
+			for group_id in group_ids {
+				GROUP_MEMBERS.insert((user_id.clone(), *group_id), CachedGroupMember {
+					fetched_at,
+					member: fetched
+						.members
+						.iter()
+						.find(|x| x.user_id == user_id && x.group_id == *group_id)
+						.cloned()
+				});
+			}
+		}
+		members.extend(fetched.members);
+
+		return Ok(GroupMembers {
+			members,
+			failed_user_ids: fetched.failed_user_ids
+		});
+	}
+
+	Ok(GroupMembers {
+		members,
+		failed_user_ids: vec![]
+	})
+}
+
+/// Open Cloud is queried with batches of users per group, if that fails, each user is looked up individually instead.
+/// A user failing to be looked up doesn't stop the others, unless every single one fails.
+async fn fetch_group_members(group_ids: &[u64], user_ids: &[String]) -> Result<GroupMembers> {
+	match get_group_members_batched(group_ids, user_ids).await {
+		Ok(result) => Ok(result),
+		Err(error) => {
+			tracing::warn!(%error, "batched roblox group membership lookup failed, falling back to per-user lookup");
+
+			let mut result = GroupMembers::default();
+			let mut last_error = None;
+			for user_id in user_ids {
+				match get_user_group_roles(user_id).await {
+					Ok(roles) => result.members.extend(roles
+						.into_iter()
+						.filter(|x| group_ids.contains(&x.group.id))
+						.map(|x| x.into_group_member(user_id))
+					),
+					Err(error) => {
+						tracing::warn!(%user_id, %error, "roblox group membership lookup failed for user");
+						result.failed_user_ids.push(user_id.clone());
+						last_error = Some(error);
+					}
+				}
+			}
+
+			if result.failed_user_ids.len() == user_ids.len() && let Some(error) = last_error {
+				return Err(error);
+			}
+
+			Ok(result)
+		}
+	}
+}
+
+async fn get_group_members_batched(group_ids: &[u64], user_ids: &[String]) -> Result<GroupMembers> {
+	let mut result = GroupMembers::default();
+	// memberships with a role that isn't among the group's ranks, such as one created in between the two requests.
+	let mut unranked_memberships: Vec<(String, u64)> = vec![];
+	for group_id in group_ids {
+		// memberships only reference their role, so ranks are looked up separately.
+		let ranks = get_group_ranks(*group_id)
+			.await?;
+		for chunk in user_ids.chunks(MEMBERSHIP_FILTER_BATCH_SIZE) {
+			let filter = format!("user in ['{}']", chunk
+				.iter()
+				.map(|x| format!("users/{x}"))
+				.collect::<Vec<String>>()
+				.join("','")
+			);
+			for membership in get_group_memberships(*group_id, Some(&filter)).await? {
+				if let Some(user_id) = membership.user_id() && let Some(role_id) = membership.role_id() {
+					match ranks.get(&role_id) {
+						Some(rank) => result.members.push(GroupMember {
+							user_id: user_id.to_string(),
+							group_id: *group_id,
+							role_id,
+							rank: *rank
+						}),
+						None => {
+							tracing::warn!(%group_id, %role_id, %user_id, "roblox group role has no known rank, looking the user up individually");
+							GROUP_RANKS.remove(group_id);
+							unranked_memberships.push((user_id.to_string(), *group_id));
+						}
+					}
+				}
+			}
+		}
+	}
+
+	// the per-user endpoint includes ranks, so it's used for these instead.
+	let mut unranked_user_ids: Vec<&String> = unranked_memberships
+		.iter()
+		.map(|x| &x.0)
+		.collect();
+	unranked_user_ids.sort();
+	unranked_user_ids.dedup();
+	for user_id in unranked_user_ids {
+		match get_user_group_roles(user_id).await {
+			Ok(roles) => result.members.extend(roles
+				.into_iter()
+				.filter(|x| unranked_memberships.contains(&(user_id.clone(), x.group.id)))
+				.map(|x| x.into_group_member(user_id))
+			),
+			Err(error) => {
+				tracing::warn!(%user_id, %error, "roblox group membership lookup failed for user");
+				result.failed_user_ids.push(user_id.clone());
+			}
+		}
+	}
+
+	Ok(result)
+}
+
+#[derive(Deserialize, Debug)]
+pub struct UserGroupRole {
+	pub role: PartialGroupRole,
+	pub group: PartialGroup
+}
+
+impl UserGroupRole {
+	fn into_group_member(self, user_id: &str) -> GroupMember {
+		GroupMember {
+			user_id: user_id.to_string(),
+			group_id: self.group.id,
+			role_id: self.role.id,
+			rank: self.role.rank
+		}
+	}
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PartialGroup {
+	pub id: u64
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PartialGroupRole {
+	pub id: u64,
+	pub rank: u8
+}
+
+#[derive(Deserialize, Debug)]
+pub struct UserGroupRolesResponse {
+	data: Vec<UserGroupRole>
+}
+
+pub async fn get_user_group_roles(user_id: impl Into<String>) -> Result<Vec<UserGroupRole>> {
+	Ok(CLIENT
+		.get_json::<UserGroupRolesResponse>(format!("{}/v2/users/{}/groups/roles", *GROUPS_URL, user_id.into()), &[])
+		.await?
+		.data
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use once_cell::sync::Lazy;
+	use std::{
+		io::{ BufRead, BufReader, Write },
+		net::TcpListener
+	};
+
+	use super::{ get_group_members, GROUP_MEMBERS };
+
+	// a minimal stand-in for the roblox apis, where batched membership lookups fail for every group but 9,
+	// and per-user lookups fail for user ids starting with "missing".
+	static STAND_IN: Lazy<()> = Lazy::new(|| {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let url = format!("http://{}", listener.local_addr().unwrap());
+		// SAFETY: set once, before any of the urls are read, the only other variables tests set are the github stand-in's own.
+		unsafe {
+			std::env::set_var("ROBLOX_OPEN_CLOUD_URL", &url);
+			std::env::set_var("ROBLOX_GROUPS_URL", &url);
+		}
+
+		std::thread::spawn(move || {
+			for stream in listener.incoming() {
+				let Ok(mut stream) = stream else {
+					continue;
+				};
+				let mut request_line = String::new();
+				let mut reader = BufReader::new(&stream);
+				reader.read_line(&mut request_line).unwrap();
+				// the rest of the request is irrelevant, but has to be read before responding.
+				let mut line = String::new();
+				while reader.read_line(&mut line).unwrap() > 2 {
+					line.clear();
+				}
+
+				let path = request_line
+					.split(' ')
+					.nth(1)
+					.unwrap_or_default()
+					.split('?')
+					.next()
+					.unwrap_or_default();
+				let (status, body) = respond(path);
+				write!(stream, "HTTP/1.1 {status}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}", body.len()).unwrap();
+			}
+		});
+	});
+
+	fn respond(path: &str) -> (&'static str, String) {
+		let segments: Vec<&str> = path
+			.trim_matches('/')
+			.split('/')
+			.collect();
+		match segments.as_slice() {
+			// role 95 was created after the roles were listed.
+			["cloud", "v2", "groups", "9", "roles"] =>
+				("200 OK", r#"{"groupRoles":[{"id":"90","rank":2,"displayName":"Member"}]}"#.into()),
+			["cloud", "v2", "groups", "9", "memberships"] =>
+				("200 OK", r#"{"groupMemberships":[
+					{"path":"groups/9/memberships/1","role":"groups/9/roles/90","user":"users/401"},
+					{"path":"groups/9/memberships/2","role":"groups/9/roles/95","user":"users/402"},
+					{"path":"groups/9/memberships/3","role":"groups/9/roles/95","user":"users/missing-403"}
+				]}"#.into()),
+			["cloud", "v2", "groups", _, "roles"] =>
+				("200 OK", r#"{"groupRoles":[{"id":"70","rank":5,"displayName":"Member"}]}"#.into()),
+			["v2", "users", user_id, "groups", "roles"] if user_id.starts_with("missing") =>
+				("404 Not Found", "{}".into()),
+			["v2", "users", _, "groups", "roles"] =>
+				("200 OK", r#"{"data":[{"role":{"id":70,"rank":5},"group":{"id":7}},{"role":{"id":80,"rank":9},"group":{"id":8}},{"role":{"id":95,"rank":3},"group":{"id":9}}]}"#.into()),
+			_ => ("400 Bad Request", "{}".into())
+		}
+	}
+
+	#[tokio::test]
+	async fn failing_users_dont_stop_the_fallback() {
+		Lazy::force(&STAND_IN);
+
+		let user_ids = vec!["101".to_string(), "missing-102".into(), "103".into()];
+		let result = get_group_members(&[7], &user_ids)
+			.await
+			.unwrap();
+		let mut members: Vec<(&str, u64, u8)> = result.members
+			.iter()
+			.map(|x| (x.user_id.as_str(), x.group_id, x.rank))
+			.collect();
+		members.sort();
+		assert_eq!(members, [("101", 7, 5), ("103", 7, 5)]);
+		assert_eq!(result.failed_user_ids, ["missing-102"]);
+
+		// users that failed aren't cached, so they're looked up again next time.
+		assert!(GROUP_MEMBERS.contains_key(&("101".into(), 7)));
+		assert!(!GROUP_MEMBERS.contains_key(&("missing-102".into(), 7)));
+	}
+
+	#[tokio::test]
+	async fn every_user_failing_is_an_error() {
+		Lazy::force(&STAND_IN);
+
+		let user_ids = vec!["missing-201".to_string(), "missing-202".into()];
+		assert!(get_group_members(&[7], &user_ids).await.is_err());
+	}
+
+	#[tokio::test]
+	async fn unranked_memberships_are_looked_up_individually() {
+		Lazy::force(&STAND_IN);
+
+		let user_ids = vec!["401".to_string(), "402".into(), "missing-403".into()];
+		let result = get_group_members(&[9], &user_ids)
+			.await
+			.unwrap();
+		let mut members: Vec<(&str, u64, u8)> = result.members
+			.iter()
+			.map(|x| (x.user_id.as_str(), x.role_id, x.rank))
+			.collect();
+		members.sort();
+		assert_eq!(members, [("401", 90, 2), ("402", 95, 3)]);
+		assert_eq!(result.failed_user_ids, ["missing-403"]);
+	}
+
+	#[tokio::test]
+	async fn cached_groups_are_kept_when_fetching_others() {
+		Lazy::force(&STAND_IN);
+
+		let user_ids = vec!["301".to_string()];
+		get_group_members(&[7], &user_ids)
+			.await
+			.unwrap();
+		get_group_members(&[8], &user_ids)
+			.await
+			.unwrap();
+		assert!(GROUP_MEMBERS.contains_key(&("301".into(), 7)));
+		assert!(GROUP_MEMBERS.contains_key(&("301".into(), 8)));
+
+		let mut groups: Vec<u64> = get_group_members(&[7, 8], &user_ids)
+			.await
+			.unwrap()
+			.members
+			.iter()
+			.map(|x| x.group_id)
+			.collect();
+		groups.sort();
+		assert_eq!(groups, [7, 8]);
+	}
+}
@@ -1,4 +1,4 @@
-use chrono::Utc;
+use chrono::{ DateTime, Utc };
 use mellow_cache::CACHE;
 use mellow_models::discord::UserModel;
 use mellow_util::{
@@ -22,7 +22,7 @@ use twilight_validate::message::EMBED_COUNT_LIMIT;
 
 use super::action_log::ActionLog;
 use crate::{
-	syncing::{ NicknameChange, RoleChange, RoleChangeKind, SyncingInitiator },
+	syncing::{ DocumentResult, NicknameChange, RoleChange, RoleChangeKind, SyncingInitiator, UnmanageableRole, UnmoderatableReason },
 	visual_scripting::ActionTrackerItem,
 	Error, Result
 };
@@ -39,7 +39,12 @@ pub enum ServerLog {
 		user_id: Id<UserMarker>,
 		role_changes: Vec<RoleChange>,
 		nickname_change: Option<NicknameChange>,
-		relevant_connections: Vec<HakuId<ConnectionMarker>>
+		timeout_until: Option<DateTime<Utc>>,
+		relevant_connections: Vec<HakuId<ConnectionMarker>>,
+		document_results: Vec<DocumentResult>,
+		unmanageable_roles: Vec<UnmanageableRole>,
+		unmoderatable_reasons: Vec<UnmoderatableReason>,
+		undelivered_removal_message: bool
 	} = 1 << 1,
 	#[serde(skip)]
 	UserCompletedOnboarding {
@@ -49,7 +54,23 @@ pub enum ServerLog {
 	VisualScriptingDocumentResult {
 		items: Vec<ActionTrackerItem>,
 		document_name: String
-	} = 1 << 3
+	} = 1 << 3,
+	#[serde(skip)]
+	SyncRollback {
+		initiator_id: Option<Id<UserMarker>>,
+		user_id: Id<UserMarker>,
+		role_changes: Vec<RoleChange>,
+		nickname_change: Option<NicknameChange>,
+		unbanned: bool,
+		timeout_removed: bool,
+		unmanageable_roles: Vec<UnmanageableRole>
+	} = 1 << 4,
+	#[serde(skip)]
+	EnforcedRoles {
+		user_id: Id<UserMarker>,
+		editor_id: Option<Id<UserMarker>>,
+		role_changes: Vec<RoleChange>
+	} = 1 << 5
 }
 
 impl ServerLog {
@@ -116,16 +137,17 @@ pub async fn send_logs(guild_id: Id<GuildMarker>, logs: Vec<ServerLog>) -> Resul
 							.build()
 						);
 					},
-					ServerLog::ServerProfileSync { kind, initiator, user_id, role_changes, nickname_change, relevant_connections } => {
+					ServerLog::ServerProfileSync { kind, initiator, user_id, role_changes, nickname_change, timeout_until, relevant_connections, document_results, unmanageable_roles, unmoderatable_reasons, undelivered_removal_message } => {
 						let title = match kind {
 							ProfileSyncKind::Default => match initiator {
 								SyncingInitiator::Automatic =>
 									format!("<@{user_id}> was automatically synced"),
-								SyncingInitiator::ForcedBy(other_user_id) =>
+								SyncingInitiator::ForcedBy(other_user_id) |
+								SyncingInitiator::ForcedBySyncJob { initiator_id: other_user_id, .. } =>
 									format!("<@{other_user_id}> forcefully synced <@{user_id}>'s profile"),
 								SyncingInitiator::Manual =>
 									format!("<@{user_id}> synced their profile"),
-								SyncingInitiator::VisualScriptingDocument(document_id) => {
+								SyncingInitiator::VisualScriptingDocument { document_id, .. } => {
 									let document = CACHE
 										.hakumi
 										.visual_scripting_document(document_id)
@@ -142,23 +164,23 @@ pub async fn send_logs(guild_id: Id<GuildMarker>, logs: Vec<ServerLog>) -> Resul
 							.description(format!("### {title}"))
 							.footer(embed_footer(user_id, Some("Member Sync Result")).await?)
 							.timestamp(Timestamp::from_secs(Utc::now().timestamp())?);
-						if !role_changes.is_empty() {
+						if undelivered_removal_message {
 							embed = embed.field(EmbedField {
-								name: "Role changes".into(),
-								value: format!("```diff\n{}```", role_changes.iter().map(|x| match x.kind {
-									RoleChangeKind::Added => format!("+ {}", x.display_name),
-									RoleChangeKind::Removed => format!("- {}", x.display_name)
-								}).collect::<Vec<String>>().join("\n")),
+								name: "Direct message".into(),
+								value: "The member couldn't be notified beforehand, they likely have direct messages closed.".into(),
 								inline: false
 							});
 						}
+						if !role_changes.is_empty() {
+							embed = embed.field(role_changes_field(&role_changes));
+						}
 						if let Some(changes) = nickname_change {
+							embed = embed.field(nickname_change_field(changes));
+						}
+						if let Some(timeout_until) = timeout_until {
 							embed = embed.field(EmbedField {
-								name: "Nickname changes".into(),
-								value: format!("```diff{}{}```",
-									changes.0.map(|x| format!("\n- {x}")).unwrap_or("".into()),
-									changes.1.map(|x| format!("\n+ {x}")).unwrap_or("".into())
-								),
+								name: "Timeout".into(),
+								value: format!("Timed out until <t:{}:f>", timeout_until.timestamp()),
 								inline: false
 							});
 						}
@@ -173,6 +195,27 @@ pub async fn send_logs(guild_id: Id<GuildMarker>, logs: Vec<ServerLog>) -> Resul
 								inline: false
 							});
 						}
+						if !unmanageable_roles.is_empty() {
+							embed = embed.field(unmanageable_roles_field(&unmanageable_roles));
+						}
+						if !unmoderatable_reasons.is_empty() {
+							embed = embed.field(EmbedField {
+								name: "Skipped moderation".into(),
+								value: unmoderatable_reasons
+									.iter()
+									.map(|x| format!("A ban, kick or timeout was skipped, as {}.", x.display()))
+									.collect::<Vec<String>>()
+									.join("\n"),
+								inline: false
+							});
+						}
+						for document_result in document_results {
+							embed = embed.field(EmbedField {
+								name: format!("Result for {}", document_result.document_name),
+								value: document_result.items.join("\n"),
+								inline: false
+							});
+						}
 
 						embeds.push(embed.build());
 					},
@@ -197,29 +240,60 @@ pub async fn send_logs(guild_id: Id<GuildMarker>, logs: Vec<ServerLog>) -> Resul
 							})
 							.description(items
 								.into_iter()
-								.map(|x| match x {
-									ActionTrackerItem::Error(element_kind, source) =>
-										format!("* Encountered an error at **{}**\n```diff\n- {}```\n", element_kind.display_name(), source),
-									ActionTrackerItem::AssignedMemberRole(user_id, role_id) =>
-										format!("* Assigned <@&{role_id}> to <@{user_id}>"),
-									ActionTrackerItem::RemovedMemberRole(user_id, role_id) =>
-										format!("* Removed <@&{role_id}> from <@{user_id}>"),
-									ActionTrackerItem::BannedMember(user_id) =>
-										format!("* Banned <@{user_id}> from the server"),
-									ActionTrackerItem::KickedMember(user_id) =>
-										format!("* Kicked <@{user_id}> from the server"),
-									ActionTrackerItem::CreatedMessage(channel_id, message_id) =>
-										format!("* Sent a message in <#{channel_id}>: https://discord.com/channels/{guild_id}/{channel_id}/{message_id}"),
-									ActionTrackerItem::DeletedMessage(channel_id, user_id) =>
-										format!("* Deleted a message in <#{channel_id}> by <@{user_id}>"),
-									ActionTrackerItem::CreatedThread(channel_id, thread_id) =>
-										format!("* Started a new thread in <#{channel_id}>: <#{thread_id}>")
-								})
+								.map(|x| x.display(guild_id))
 								.collect::<Vec<String>>()
 								.join("\n")
 							)
 							.build()
 						);
+					},
+					ServerLog::SyncRollback { initiator_id, user_id, role_changes, nickname_change, unbanned, timeout_removed, unmanageable_roles } => {
+						let title = match initiator_id {
+							Some(initiator_id) => format!("<@{initiator_id}> rolled back a sync of <@{user_id}>"),
+							None => format!("A sync of <@{user_id}> was rolled back")
+						};
+						let mut embed = EmbedBuilder::new()
+							.description(format!("### {title}"))
+							.footer(embed_footer(user_id, Some("Sync Rollback")).await?)
+							.timestamp(Timestamp::from_secs(Utc::now().timestamp())?);
+						if unbanned {
+							embed = embed.field(EmbedField {
+								name: "Ban".into(),
+								value: "The member has been unbanned.".into(),
+								inline: false
+							});
+						}
+						if timeout_removed {
+							embed = embed.field(EmbedField {
+								name: "Timeout".into(),
+								value: "The member's timeout has been removed.".into(),
+								inline: false
+							});
+						}
+						if !role_changes.is_empty() {
+							embed = embed.field(role_changes_field(&role_changes));
+						}
+						if let Some(changes) = nickname_change {
+							embed = embed.field(nickname_change_field(changes));
+						}
+						if !unmanageable_roles.is_empty() {
+							embed = embed.field(unmanageable_roles_field(&unmanageable_roles));
+						}
+
+						embeds.push(embed.build());
+					},
+					ServerLog::EnforcedRoles { user_id, editor_id, role_changes } => {
+						let title = match editor_id {
+							Some(editor_id) => format!("Reverted changes by <@{editor_id}> to the roles of <@{user_id}>"),
+							None => format!("Reverted manual changes to the roles of <@{user_id}>")
+						};
+						embeds.push(EmbedBuilder::new()
+							.description(format!("### {title}\nThese roles are enforced by sync actions."))
+							.field(role_changes_field(&role_changes))
+							.footer(embed_footer(user_id, Some("Role Enforcement")).await?)
+							.timestamp(Timestamp::from_secs(Utc::now().timestamp())?)
+							.build()
+						);
 					}
 				}
 			}
@@ -237,6 +311,40 @@ pub async fn send_logs(guild_id: Id<GuildMarker>, logs: Vec<ServerLog>) -> Resul
 	Ok(())
 }
 
+fn role_changes_field(role_changes: &[RoleChange]) -> EmbedField {
+	EmbedField {
+		name: "Role changes".into(),
+		value: format!("```diff\n{}```", role_changes.iter().map(|x| match x.kind {
+			RoleChangeKind::Added => format!("+ {}", x.display_name),
+			RoleChangeKind::Removed => format!("- {}", x.display_name)
+		}).collect::<Vec<String>>().join("\n")),
+		inline: false
+	}
+}
+
+fn nickname_change_field(changes: NicknameChange) -> EmbedField {
+	EmbedField {
+		name: "Nickname changes".into(),
+		value: format!("```diff{}{}```",
+			changes.0.map(|x| format!("\n- {x}")).unwrap_or("".into()),
+			changes.1.map(|x| format!("\n+ {x}")).unwrap_or("".into())
+		),
+		inline: false
+	}
+}
+
+fn unmanageable_roles_field(unmanageable_roles: &[UnmanageableRole]) -> EmbedField {
+	EmbedField {
+		name: "Skipped roles".into(),
+		value: unmanageable_roles
+			.iter()
+			.map(|x| format!("<@&{}> — {}", x.role_id, x.reason.display()))
+			.collect::<Vec<String>>()
+			.join("\n"),
+		inline: false
+	}
+}
+
 fn embed_author(guild_id: Id<GuildMarker>, user: &UserModel, title: Option<String>) -> EmbedAuthor {
 	EmbedAuthor {
 		url: Some(format!("https://hakumi.cafe/mellow/server/{}/member/{}", guild_id, user.id)),
@@ -0,0 +1,298 @@
+use mellow_cache::CACHE;
+use mellow_models::mellow::server::sync_action::{ CriteriaItem, SyncActionModel };
+use mellow_util::hakuid::{
+	marker::SyncActionMarker,
+	HakuId
+};
+use std::collections::HashMap;
+use twilight_model::id::{
+	marker::GuildMarker,
+	Id
+};
+
+use crate::Result;
+
+/// A server's sync actions in priority order, with an evaluation order in which every action comes after the actions its criteria depend on.
+/// Actions that depend on each other in a cycle can't be evaluated, so they're set aside in [`Self::cycles`] instead,
+/// anything depending on them treats them as not met.
+#[derive(Debug)]
+pub struct SyncActionGraph {
+	actions: Vec<SyncActionModel>,
+	evaluation_order: Vec<usize>,
+	cycles: Vec<Vec<SyncActionModel>>
+}
+
+impl SyncActionGraph {
+	pub async fn get(guild_id: Id<GuildMarker>) -> Result<Self> {
+		let action_ids = CACHE
+			.mellow
+			.server_sync_actions(guild_id)
+			.await?;
+		let actions: Vec<_> = CACHE
+			.mellow
+			.sync_actions(&action_ids)
+			.await?
+			.into_iter()
+			.map(|x| x.clone())
+			.collect();
+		Ok(Self::new(actions))
+	}
+
+	pub fn new(mut actions: Vec<SyncActionModel>) -> Self {
+		// ties are broken by id, so the order never depends on how the actions were loaded.
+		actions.sort_by_key(|x| (x.priority, x.id));
+
+		let indices: HashMap<HakuId<SyncActionMarker>, usize> = actions
+			.iter()
+			.enumerate()
+			.map(|(index, action)| (action.id, index))
+			.collect();
+		// references to actions that no longer exist are left out, they're simply never met.
+		let dependencies: Vec<Vec<usize>> = actions
+			.iter()
+			.map(|action| action
+				.criteria
+				.leaf_items()
+				.into_iter()
+				.filter_map(|item| match item {
+					CriteriaItem::MellowServerSyncingActions { action_ids, .. } => Some(action_ids),
+					_ => None
+				})
+				.flatten()
+				.filter_map(|x| indices.get(x).copied())
+				.collect()
+			)
+			.collect();
+
+		let mut is_cyclic = vec![false; actions.len()];
+		let mut evaluation_order = Vec::with_capacity(actions.len());
+		let mut cycles: Vec<Vec<usize>> = vec![];
+		for component in strongly_connected_components(&dependencies) {
+			if component.len() > 1 || dependencies[component[0]].contains(&component[0]) {
+				for index in &component {
+					is_cyclic[*index] = true;
+				}
+				cycles.push(component);
+			} else {
+				evaluation_order.push(component[0]);
+			}
+		}
+
+		// actions in a cycle are moved out, so every index left needs to be shifted down accordingly.
+		let mut new_indices: Vec<usize> = Vec::with_capacity(actions.len());
+		let mut cyclic_actions: HashMap<usize, SyncActionModel> = HashMap::new();
+		let mut remaining_actions = Vec::with_capacity(actions.len());
+		for (index, action) in actions.into_iter().enumerate() {
+			new_indices.push(remaining_actions.len());
+			if is_cyclic[index] {
+				cyclic_actions.insert(index, action);
+			} else {
+				remaining_actions.push(action);
+			}
+		}
+
+		Self {
+			actions: remaining_actions,
+			evaluation_order: evaluation_order
+				.into_iter()
+				.map(|x| new_indices[x])
+				.collect(),
+			cycles: cycles
+				.into_iter()
+				.map(|mut component| {
+					component.sort();
+					component
+						.into_iter()
+						.filter_map(|x| cyclic_actions.remove(&x))
+						.collect()
+				})
+				.collect()
+		}
+	}
+
+	pub fn actions(&self) -> &[SyncActionModel] {
+		&self.actions
+	}
+
+	pub fn evaluation_order(&self) -> impl Iterator<Item = &SyncActionModel> {
+		self.evaluation_order
+			.iter()
+			.map(|x| &self.actions[*x])
+	}
+
+	/// Groups of actions that depend on each other, each in priority order.
+	pub fn cycles(&self) -> &[Vec<SyncActionModel>] {
+		&self.cycles
+	}
+}
+
+// an iterative take on tarjan's algorithm, components come out after every component they depend on.
+fn strongly_connected_components(dependencies: &[Vec<usize>]) -> Vec<Vec<usize>> {
+	let mut visit_indices: Vec<Option<usize>> = vec![None; dependencies.len()];
+	let mut low_links = vec![0; dependencies.len()];
+	let mut on_stack = vec![false; dependencies.len()];
+	let mut component_stack: Vec<usize> = vec![];
+	let mut next_visit_index = 0;
+	let mut components = vec![];
+	for root in 0..dependencies.len() {
+		if visit_indices[root].is_some() {
+			continue;
+		}
+
+		// each entry holds the index of the next dependency to visit.
+		let mut stack: Vec<(usize, usize)> = vec![(root, 0)];
+		visit_indices[root] = Some(next_visit_index);
+		low_links[root] = next_visit_index;
+		next_visit_index += 1;
+		component_stack.push(root);
+		on_stack[root] = true;
+		while let Some(&(node, position)) = stack.last() {
+			if let Some(&dependency) = dependencies[node].get(position) {
+				stack.last_mut().unwrap().1 += 1;
+				match visit_indices[dependency] {
+					None => {
+						visit_indices[dependency] = Some(next_visit_index);
+						low_links[dependency] = next_visit_index;
+						next_visit_index += 1;
+						component_stack.push(dependency);
+						on_stack[dependency] = true;
+						stack.push((dependency, 0));
+					},
+					Some(visit_index) => if on_stack[dependency] {
+						low_links[node] = low_links[node].min(visit_index);
+					}
+				}
+			} else {
+				stack.pop();
+				if let Some(&(parent, _)) = stack.last() {
+					low_links[parent] = low_links[parent].min(low_links[node]);
+				}
+				if visit_indices[node] == Some(low_links[node]) {
+					let mut component = vec![];
+					while let Some(member) = component_stack.pop() {
+						on_stack[member] = false;
+						component.push(member);
+						if member == node {
+							break;
+						}
+					}
+					components.push(component);
+				}
+			}
+		}
+	}
+
+	components
+}
+
+#[cfg(test)]
+mod tests {
+	use mellow_models::mellow::server::sync_action::{ Criteria, CriteriaItem, Quantifier, SyncActionKind, SyncActionModel };
+	use mellow_util::hakuid::HakuId;
+	use uuid::Uuid;
+
+	use super::SyncActionGraph;
+
+	fn action(id: u128, priority: i32, depends_on: &[u128]) -> SyncActionModel {
+		SyncActionModel {
+			id: HakuId::new(Uuid::from_u128(id)),
+			kind: SyncActionKind::AssignRoles {
+				role_ids: vec![],
+				can_remove: false,
+				enforced: false
+			},
+			criteria: Criteria {
+				items: if depends_on.is_empty() { vec![] } else {
+					vec![CriteriaItem::MellowServerSyncingActions {
+						action_ids: depends_on
+							.iter()
+							.map(|x| HakuId::new(Uuid::from_u128(*x)))
+							.collect(),
+						quantifier: Quantifier::All
+					}]
+				},
+				quantifier: Quantifier::All
+			},
+			display_name: format!("action {id}"),
+			priority,
+			role_group: None
+		}
+	}
+
+	fn ids<'a>(actions: impl IntoIterator<Item = &'a SyncActionModel>) -> Vec<u128> {
+		actions
+			.into_iter()
+			.map(|x| x.id.value.as_u128())
+			.collect()
+	}
+
+	#[test]
+	fn actions_are_ordered_by_priority_then_id() {
+		let graph = SyncActionGraph::new(vec![
+			action(3, 1, &[]),
+			action(2, 0, &[]),
+			action(1, 1, &[])
+		]);
+		assert_eq!(ids(graph.actions()), [2, 1, 3]);
+		assert_eq!(ids(graph.evaluation_order()), [2, 1, 3]);
+	}
+
+	#[test]
+	fn dependencies_are_evaluated_first() {
+		let graph = SyncActionGraph::new(vec![
+			action(1, 0, &[2]),
+			action(2, 1, &[3]),
+			action(3, 2, &[])
+		]);
+		assert_eq!(ids(graph.actions()), [1, 2, 3]);
+		assert_eq!(ids(graph.evaluation_order()), [3, 2, 1]);
+		assert!(graph.cycles().is_empty());
+	}
+
+	#[test]
+	fn missing_dependencies_are_ignored() {
+		let graph = SyncActionGraph::new(vec![
+			action(1, 0, &[9])
+		]);
+		assert_eq!(ids(graph.evaluation_order()), [1]);
+		assert!(graph.cycles().is_empty());
+	}
+
+	#[test]
+	fn cycles_are_set_aside() {
+		let graph = SyncActionGraph::new(vec![
+			action(1, 0, &[]),
+			action(2, 1, &[3]),
+			action(3, 2, &[4]),
+			action(4, 3, &[2]),
+			action(5, 4, &[2, 1])
+		]);
+		assert_eq!(ids(graph.actions()), [1, 5]);
+		assert_eq!(ids(graph.evaluation_order()), [1, 5]);
+		assert_eq!(graph.cycles().len(), 1);
+		assert_eq!(ids(&graph.cycles()[0]), [2, 3, 4]);
+	}
+
+	#[test]
+	fn self_dependencies_are_cycles() {
+		let graph = SyncActionGraph::new(vec![
+			action(1, 0, &[1]),
+			action(2, 1, &[])
+		]);
+		assert_eq!(ids(graph.actions()), [2]);
+		assert_eq!(graph.cycles().len(), 1);
+		assert_eq!(ids(&graph.cycles()[0]), [1]);
+	}
+
+	#[test]
+	fn separate_cycles_are_reported_separately() {
+		let graph = SyncActionGraph::new(vec![
+			action(1, 0, &[2]),
+			action(2, 1, &[1]),
+			action(3, 2, &[4]),
+			action(4, 3, &[3])
+		]);
+		assert!(graph.actions().is_empty());
+		assert_eq!(graph.cycles().len(), 2);
+	}
+}
@@ -0,0 +1,169 @@
+use chrono::Utc;
+use dashmap::{ DashMap, DashSet };
+use mellow_cache::CACHE;
+use mellow_models::mellow::server::UserSettingsModel;
+use mellow_util::hakuid::{
+	marker::{ ConnectionMarker, UserMarker as HakuUserMarker },
+	HakuId
+};
+use once_cell::sync::Lazy;
+use std::time::Duration;
+use twilight_model::id::{
+	marker::{ GuildMarker, UserMarker },
+	Id
+};
+
+use super::{
+	bulk::{ is_job_running, syncs_unlinked_members },
+	ConnectionMetadata, SyncActionGraph, SyncingInitiator, SyncMemberResult,
+	sync_member, sync_single_user
+};
+use crate::{
+	server::logging::send_logs,
+	Result
+};
+
+// how long a member waits to be synced after a connection change, so a burst of changes only syncs them once.
+const CONNECTION_SYNC_DELAY: Duration = Duration::from_secs(10);
+
+// members waiting on CONNECTION_SYNC_DELAY, any change in the meantime is picked up by that same sync.
+static PENDING_CONNECTION_SYNCS: Lazy<DashSet<(Id<GuildMarker>, Id<UserMarker>)>> = Lazy::new(DashSet::new);
+
+// unlinked members still within their grace period, along with the unix timestamp it ends at.
+// this is only kept in memory, members are scheduled again whenever they're synced, such as by the next resync.
+static PENDING_UNLINKED_KICKS: Lazy<DashMap<(Id<GuildMarker>, Id<UserMarker>), i64>> = Lazy::new(DashMap::new);
+
+fn auto_syncs_members(guild_id: Id<GuildMarker>) -> bool {
+	CACHE
+		.mellow
+		.server(guild_id)
+		.is_some_and(|x| x.auto_sync_members)
+}
+
+/// Syncs a member that has just joined the server, or just got through membership screening.
+/// Linked members are only synced when the server has automatic syncing enabled, unlinked ones when it has a policy for them.
+pub async fn sync_new_member(guild_id: Id<GuildMarker>, member_id: Id<UserMarker>) -> Result<()> {
+	// a forceful sync of the entire server gets to them anyway.
+	if is_job_running(guild_id) {
+		return Ok(());
+	}
+
+	match CACHE.hakumi.user_by_discord(guild_id, member_id).await? {
+		Some(user_id) if auto_syncs_members(guild_id) => {
+			let result = sync_single_user(guild_id, user_id, member_id, SyncingInitiator::Automatic, None, false, false)
+				.await?;
+			send_result_log(guild_id, result)
+				.await
+		},
+		None => sync_unlinked_member(guild_id, member_id)
+			.await,
+		_ => Ok(())
+	}
+}
+
+/// Syncs the member again once the given unix timestamp has passed, so the server's unlinked member policy can kick them on time.
+pub fn schedule_unlinked_kick(guild_id: Id<GuildMarker>, member_id: Id<UserMarker>, kick_at: i64) {
+	PENDING_UNLINKED_KICKS.insert((guild_id, member_id), kick_at);
+}
+
+pub fn cancel_unlinked_kick(guild_id: Id<GuildMarker>, member_id: Id<UserMarker>) {
+	PENDING_UNLINKED_KICKS.remove(&(guild_id, member_id));
+}
+
+/// Syncs every scheduled member whose grace period is over, members that linked in the meantime are left alone.
+pub async fn sync_overdue_unlinked_members() {
+	let now = Utc::now().timestamp();
+	let mut overdue_members: Vec<(Id<GuildMarker>, Id<UserMarker>)> = vec![];
+	PENDING_UNLINKED_KICKS.retain(|key, kick_at| if *kick_at < now {
+		overdue_members.push(*key);
+		false
+	} else { true });
+
+	for (guild_id, member_id) in overdue_members {
+		// a forceful sync of the entire server gets to them anyway.
+		if is_job_running(guild_id) {
+			continue;
+		}
+
+		let result: Result<()> = try {
+			if CACHE.hakumi.user_by_discord(guild_id, member_id).await?.is_none() {
+				sync_unlinked_member(guild_id, member_id)
+					.await?;
+			}
+		};
+		if let Err(error) = result {
+			tracing::warn!(%guild_id, %member_id, %error, "failed to sync unlinked member");
+		}
+	}
+}
+
+async fn sync_unlinked_member(guild_id: Id<GuildMarker>, member_id: Id<UserMarker>) -> Result<()> {
+	if !syncs_unlinked_members(guild_id) {
+		return Ok(());
+	}
+
+	let actions = SyncActionGraph::get(guild_id)
+		.await?;
+	let result = sync_member(guild_id, None, member_id, SyncingInitiator::Automatic, &actions, &ConnectionMetadata::default(), false, false)
+		.await?;
+	send_result_log(guild_id, result)
+		.await
+}
+
+/// Syncs the user in every server they've shared the given connection with, that has automatic syncing enabled.
+/// Syncs are delayed by [`CONNECTION_SYNC_DELAY`], changes made while one is waiting don't schedule another.
+pub async fn sync_connection_servers(user_id: HakuId<HakuUserMarker>, connection_id: HakuId<ConnectionMarker>) -> Result<()> {
+	let user_connections = CACHE
+		.hakumi
+		.user_connections(&[user_id])
+		.await?;
+	let Some(member_id) = CACHE
+		.hakumi
+		.connections(&user_connections)
+		.await?
+		.into_iter()
+		.find(|x| x.is_discord())
+		.and_then(|x| x.sub.parse().ok())
+		.map(Id::new)
+	else {
+		return Ok(());
+	};
+
+	for guild_id in UserSettingsModel::servers_with_connection(user_id, connection_id).await? {
+		if !auto_syncs_members(guild_id) || !PENDING_CONNECTION_SYNCS.insert((guild_id, member_id)) {
+			continue;
+		}
+
+		tokio::spawn(async move {
+			tokio::time::sleep(CONNECTION_SYNC_DELAY).await;
+			PENDING_CONNECTION_SYNCS.remove(&(guild_id, member_id));
+
+			// a forceful sync of the entire server gets to them anyway.
+			if is_job_running(guild_id) {
+				return;
+			}
+
+			let result: Result<()> = try {
+				let result = sync_single_user(guild_id, user_id, member_id, SyncingInitiator::Automatic, None, false, false)
+					.await?;
+				send_result_log(guild_id, result)
+					.await?;
+			};
+			// members that have left the server end up here too.
+			if let Err(error) = result {
+				tracing::debug!(%guild_id, %member_id, %error, "failed to automatically sync member");
+			}
+		});
+	}
+
+	Ok(())
+}
+
+async fn send_result_log(guild_id: Id<GuildMarker>, result: SyncMemberResult) -> Result<()> {
+	if let Some(result_log) = result.create_log() {
+		send_logs(guild_id, vec![result_log])
+			.await?;
+	}
+
+	Ok(())
+}
@@ -0,0 +1,420 @@
+use dashmap::{ mapref::entry::Entry, DashMap };
+use futures::{ stream, StreamExt };
+use mellow_cache::CACHE;
+use mellow_models::{
+	hakumi::user::connection::ConnectionKind,
+	mellow::{ server::SyncJobModel, ServerModel }
+};
+use mellow_util::{
+	hakuid::{
+		marker::UserMarker as HakuUserMarker,
+		HakuId
+	},
+	DISCORD_CLIENT,
+	DISCORD_INTERACTION_CLIENT
+};
+use once_cell::sync::Lazy;
+use std::{
+	collections::{ HashMap, HashSet },
+	time::Duration
+};
+use tokio::time;
+use tokio_util::sync::CancellationToken;
+use twilight_http_ratelimiting::{ Bucket, Path };
+use twilight_model::id::{
+	marker::{ GuildMarker, UserMarker },
+	Id
+};
+
+use super::{ SyncActionGraph, SyncingInitiator, get_connection_metadata, sync_member };
+use crate::{
+	server::logging::{ ServerLog, send_logs },
+	Result
+};
+
+const DEFAULT_CONCURRENCY: usize = 4;
+const MAX_CONCURRENCY: usize = 10;
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+// failures beyond this are only counted, so the final response stays within discord's length limit.
+const MAX_LISTED_FAILURES: usize = 10;
+// the most members discord returns per request.
+const MEMBER_PAGE_SIZE: u16 = 1000;
+
+// servers being synced in their entirety, by a job or a scheduled resync, see [`claim_job`].
+static RUNNING_JOBS: Lazy<DashMap<Id<GuildMarker>, CancellationToken>> = Lazy::new(DashMap::new);
+
+// jobs left over from before a restart, each is resumed once its server becomes available.
+static PENDING_JOBS: Lazy<DashMap<Id<GuildMarker>, SyncJobModel>> = Lazy::new(DashMap::new);
+
+pub fn is_job_running(guild_id: Id<GuildMarker>) -> bool {
+	RUNNING_JOBS.contains_key(&guild_id) || PENDING_JOBS.contains_key(&guild_id)
+}
+
+/// Reserves a server for a job, nothing else can sync every member of it until the claim is dropped, or the job it was handed to ends.
+pub struct JobClaim {
+	guild_id: Id<GuildMarker>,
+	cancel: Option<CancellationToken>
+}
+
+impl JobClaim {
+	fn cancel(&self) -> &CancellationToken {
+		self.cancel.as_ref().unwrap()
+	}
+
+	// the job takes over removing the server from the running jobs once it ends.
+	fn into_job(mut self) -> CancellationToken {
+		self.cancel.take().unwrap()
+	}
+}
+
+impl Drop for JobClaim {
+	fn drop(&mut self) {
+		if self.cancel.is_some() {
+			RUNNING_JOBS.remove(&self.guild_id);
+		}
+	}
+}
+
+/// Returns [`None`] if the server already has a job, running or waiting to be resumed.
+pub fn claim_job(guild_id: Id<GuildMarker>) -> Option<JobClaim> {
+	if PENDING_JOBS.contains_key(&guild_id) {
+		return None;
+	}
+	claim_running_job(guild_id)
+}
+
+fn claim_running_job(guild_id: Id<GuildMarker>) -> Option<JobClaim> {
+	match RUNNING_JOBS.entry(guild_id) {
+		Entry::Occupied(_) => None,
+		Entry::Vacant(entry) => {
+			let cancel = CancellationToken::new();
+			entry.insert(cancel.clone());
+			Some(JobClaim {
+				guild_id,
+				cancel: Some(cancel)
+			})
+		}
+	}
+}
+
+/// Maps the discord account of every user that has joined the server with mellow, to their HAKUMI user.
+pub async fn server_user_ids(guild_id: Id<GuildMarker>) -> Result<(Vec<HakuId<HakuUserMarker>>, HashMap<Id<UserMarker>, HakuId<HakuUserMarker>>)> {
+	let user_ids = ServerModel::users(guild_id)
+		.await?;
+	let user_connection_ids = CACHE
+		.hakumi
+		.user_connections(&user_ids)
+		.await?;
+	let mapped_user_ids = CACHE
+		.hakumi
+		.connections(&user_connection_ids)
+		.await?
+		.into_iter()
+		.filter(|x| x.kind == ConnectionKind::Discord)
+		.filter_map(|x| Some((Id::new(x.sub.parse().ok()?), x.user_id)))
+		.collect();
+
+	Ok((user_ids, mapped_user_ids))
+}
+
+/// Whether unlinked members should be synced too, which is only the case when the server has a policy for them.
+pub fn syncs_unlinked_members(guild_id: Id<GuildMarker>) -> bool {
+	CACHE
+		.mellow
+		.server(guild_id)
+		.is_some_and(|x| x.unlinked_member_policy.is_some())
+}
+
+/// Lists every member of the server other than bots, linked or not.
+pub async fn server_member_ids(guild_id: Id<GuildMarker>) -> Result<Vec<Id<UserMarker>>> {
+	let mut member_ids: Vec<Id<UserMarker>> = vec![];
+	let mut after: Option<Id<UserMarker>> = None;
+	loop {
+		let mut request = DISCORD_CLIENT
+			.guild_members(guild_id)
+			.limit(MEMBER_PAGE_SIZE);
+		if let Some(after) = after {
+			request = request.after(after);
+		}
+		let members = request
+			.await?
+			.models()
+			.await?;
+		let total_members = members.len();
+		after = members.last().map(|x| x.user.id);
+
+		for member in members {
+			if !member.user.bot {
+				member_ids.push(member.user.id);
+				CACHE.discord.members.insert((guild_id, member.user.id), member.into());
+			}
+		}
+		if total_members < MEMBER_PAGE_SIZE as usize {
+			break;
+		}
+	}
+
+	Ok(member_ids)
+}
+
+/// Starts syncing every given member in the background, progress is reported by editing the original interaction response.
+pub async fn start_job(claim: JobClaim, initiator_id: Id<UserMarker>, interaction_token: String, member_ids: Vec<Id<UserMarker>>) -> Result<()> {
+	let job = SyncJobModel::create(claim.guild_id, initiator_id, interaction_token, member_ids)
+		.await?;
+	spawn_job(job, claim.into_job());
+
+	Ok(())
+}
+
+pub async fn load_unfinished_jobs() -> Result<()> {
+	for job in SyncJobModel::get_all().await? {
+		tracing::info!(job_id = %job.id, guild_id = %job.server_id, "found unfinished sync job");
+		PENDING_JOBS.insert(job.server_id, job);
+	}
+
+	Ok(())
+}
+
+pub fn resume_pending_job(guild_id: Id<GuildMarker>) {
+	if let Some((_, job)) = PENDING_JOBS.remove(&guild_id) {
+		let Some(claim) = claim_running_job(guild_id) else {
+			PENDING_JOBS.insert(guild_id, job);
+			return;
+		};
+		tracing::info!(job_id = %job.id, %guild_id, "resuming sync job");
+		spawn_job(job, claim.into_job());
+	}
+}
+
+/// Returns whether there was a job to cancel.
+pub async fn cancel_job(guild_id: Id<GuildMarker>) -> Result<bool> {
+	if let Some(cancel) = RUNNING_JOBS.get(&guild_id) {
+		cancel.cancel();
+		return Ok(true);
+	}
+
+	if let Some((_, job)) = PENDING_JOBS.remove(&guild_id) {
+		job.delete()
+			.await?;
+		return Ok(true);
+	}
+
+	Ok(false)
+}
+
+fn spawn_job(job: SyncJobModel, cancel: CancellationToken) {
+	tokio::spawn(async move {
+		let guild_id = job.server_id;
+		let interaction_token = job.interaction_token.clone();
+		let result = run_job(job, cancel)
+			.await;
+		RUNNING_JOBS.remove(&guild_id);
+
+		if let Err(error) = result {
+			tracing::error!(%guild_id, %error, "sync job failed");
+			update_response(&interaction_token, &format!("<:niko_look_left:1227198516590411826> something unexpected happened while syncing members...\n```diff\n- {error}```"))
+				.await;
+		}
+	});
+}
+
+async fn run_job(mut job: SyncJobModel, cancel: CancellationToken) -> Result<()> {
+	let guild_id = job.server_id;
+	let setup: Result<_> = try {
+		let (user_ids, mapped_user_ids) = server_user_ids(guild_id)
+			.await?;
+		let actions = SyncActionGraph::get(guild_id)
+			.await?;
+		let metadata = get_connection_metadata(guild_id, &user_ids, &actions)
+			.await?;
+		(mapped_user_ids, actions, metadata)
+	};
+	let (mapped_user_ids, actions, metadata) = match setup {
+		Ok(x) => x,
+		// removed, as it would otherwise be resumed and fail the same way after every restart.
+		Err(error) => {
+			job.delete()
+				.await?;
+			return Err(error);
+		}
+	};
+	let initiator = SyncingInitiator::ForcedBySyncJob {
+		initiator_id: job.initiator_id,
+		job_id: job.id
+	};
+
+	let concurrency = member_bucket(guild_id)
+		.await
+		.map_or(DEFAULT_CONCURRENCY, |x| x.limit() as usize)
+		.clamp(1, MAX_CONCURRENCY);
+	let mut pending_member_ids: HashSet<Id<UserMarker>> = job.pending_member_ids
+		.iter()
+		.copied()
+		.collect();
+	let mut results = stream::iter(job.pending_member_ids.clone())
+		.map(|member_id| {
+			let user_id = mapped_user_ids.get(&member_id).copied();
+			let initiator = initiator.clone();
+			let actions = &actions;
+			let metadata = &metadata;
+			async move {
+				wait_for_member_bucket(guild_id).await;
+				(member_id, sync_member(guild_id, user_id, member_id, initiator, actions, metadata, false, false).await)
+			}
+		})
+		.buffer_unordered(concurrency);
+
+	let mut logs: Vec<ServerLog> = vec![];
+	let mut failures: Vec<(Id<UserMarker>, String)> = vec![];
+	let mut progress_interval = time::interval(PROGRESS_INTERVAL);
+	let mut is_cancelled = false;
+	loop {
+		tokio::select! {
+			item = results.next() => match item {
+				Some((member_id, result)) => {
+					pending_member_ids.remove(&member_id);
+					match result {
+						Ok(result) => {
+							if result.profile_changed {
+								job.total_changed += 1;
+							}
+							if let Some(result_log) = result.create_log() {
+								logs.push(result_log);
+							}
+						},
+						// a single member failing shouldn't stop everyone else from being synced.
+						Err(error) => {
+							tracing::warn!(%guild_id, %member_id, %error, "failed to sync member");
+							job.total_failed += 1;
+							if failures.len() < MAX_LISTED_FAILURES {
+								failures.push((member_id, error.to_string()));
+							}
+						}
+					}
+				},
+				None => break
+			},
+			_ = progress_interval.tick() => {
+				job.pending_member_ids = pending_member_ids.iter().copied().collect();
+				if let Err(error) = job.save_progress().await {
+					tracing::warn!(%guild_id, %error, "failed to save sync job progress");
+				}
+
+				let total_synced = job.total_members as usize - pending_member_ids.len();
+				update_response(&job.interaction_token, &format!("## Syncing profiles... {total_synced}/{}\n{} profile(s) updated and {} failed so far, use `/cancelsyncall` to stop early.",
+					job.total_members,
+					job.total_changed,
+					job.total_failed
+				)).await;
+
+				if let Err(error) = send_logs(guild_id, std::mem::take(&mut logs)).await {
+					tracing::warn!(%guild_id, %error, "failed to send sync job logs");
+				}
+			},
+			_ = cancel.cancelled() => {
+				is_cancelled = true;
+				break;
+			}
+		}
+	}
+	drop(results);
+
+	job.delete()
+		.await?;
+
+	let total_synced = job.total_members as usize - pending_member_ids.len() - job.total_failed as usize;
+	let mut content = if is_cancelled {
+		format!("## Syncing was cancelled\n{total_synced} profile(s) were synced before cancelling, {} of which were updated.", job.total_changed)
+	} else {
+		format!("## Successfully synced {total_synced} profiles\n{} profile(s) in total were updated.", job.total_changed)
+	};
+	if job.total_failed > 0 {
+		content.push_str(&format!("\n{} profile(s) couldn't be synced:\n{}", job.total_failed, failures
+			.iter()
+			.map(|(member_id, error)| format!("* <@{member_id}> — {error}"))
+			.collect::<Vec<String>>()
+			.join("\n")
+		));
+	}
+	update_response(&job.interaction_token, &content)
+		.await;
+
+	send_logs(guild_id, logs)
+		.await
+}
+
+/// Syncs every linked member of the server (and unlinked ones, see [`syncs_unlinked_members`]).
+/// This is what periodic resyncing runs, so there's no response to report progress to.
+pub async fn resync_server(guild_id: Id<GuildMarker>) -> Result<()> {
+	// a forceful sync is already covering everyone, claiming the server also stops one from starting until this is done.
+	let Some(claim) = claim_job(guild_id) else {
+		return Ok(());
+	};
+
+	let (user_ids, mapped_user_ids) = server_user_ids(guild_id)
+		.await?;
+	let actions = SyncActionGraph::get(guild_id)
+		.await?;
+	let metadata = get_connection_metadata(guild_id, &user_ids, &actions)
+		.await?;
+	let member_ids: Vec<Id<UserMarker>> = if syncs_unlinked_members(guild_id) {
+		server_member_ids(guild_id)
+			.await?
+	} else {
+		mapped_user_ids
+			.keys()
+			.copied()
+			.collect()
+	};
+	let logs: Vec<ServerLog> = stream::iter(member_ids)
+		.map(|member_id| {
+			let user_id = mapped_user_ids.get(&member_id).copied();
+			let actions = &actions;
+			let metadata = &metadata;
+			async move {
+				wait_for_member_bucket(guild_id).await;
+				match sync_member(guild_id, user_id, member_id, SyncingInitiator::Automatic, actions, metadata, false, false).await {
+					Ok(result) => result.create_log(),
+					// members that have left the server end up here too.
+					Err(error) => {
+						tracing::debug!(%guild_id, %member_id, %error, "failed to resync member");
+						None
+					}
+				}
+			}
+		})
+		.buffer_unordered(DEFAULT_CONCURRENCY)
+		.take_until(claim.cancel().cancelled())
+		.filter_map(|x| async move { x })
+		.collect()
+		.await;
+
+	send_logs(guild_id, logs)
+		.await
+}
+
+// interaction tokens expire after fifteen minutes, so responses to long (or resumed) jobs may no longer be editable.
+async fn update_response(interaction_token: &str, content: &str) {
+	if let Err(error) = DISCORD_INTERACTION_CLIENT
+		.update_response(interaction_token)
+		.content(Some(content))
+		.await
+	{
+		tracing::warn!(%error, "failed to update sync job response");
+	}
+}
+
+async fn member_bucket(guild_id: Id<GuildMarker>) -> Option<Bucket> {
+	DISCORD_CLIENT
+		.ratelimiter()?
+		.bucket(&Path::GuildsIdMembersId(guild_id.get()))
+		.await
+		.ok()?
+}
+
+// twilight queues requests on its own, waiting here keeps members from piling up behind an exhausted bucket.
+async fn wait_for_member_bucket(guild_id: Id<GuildMarker>) {
+	if let Some(bucket) = member_bucket(guild_id).await && bucket.remaining() == 0 && let Some(time_remaining) = bucket.time_remaining() {
+		time::sleep(time_remaining).await;
+	}
+}
@@ -0,0 +1,196 @@
+use dashmap::DashMap;
+use mellow_cache::CACHE;
+use mellow_models::mellow::server::SyncActionKind;
+use mellow_util::DISCORD_CLIENT;
+use once_cell::sync::Lazy;
+use std::{
+	collections::HashSet,
+	time::{ Duration, Instant }
+};
+use twilight_http::request::AuditLogReason;
+use twilight_model::{
+	guild::audit_log::AuditLogEventType,
+	id::{
+		marker::{ GuildMarker, RoleMarker, UserMarker },
+		Id
+	}
+};
+
+use super::{
+	bulk::{ is_job_running, syncs_unlinked_members },
+	ConnectionMetadata, RoleChangeKind, SyncActionGraph, SyncingInitiator,
+	sync_member, sync_single_user
+};
+use crate::{
+	server::logging::{ ServerLog, send_logs },
+	Result
+};
+
+// how long a role change made by mellow waits for its member update, before it's forgotten.
+const OWN_ROLE_CHANGE_TTL: Duration = Duration::from_secs(30);
+// how many recent role updates are searched for whoever made the manual change.
+const AUDIT_LOG_SEARCH_LIMIT: u16 = 10;
+
+// roles mellow itself is about to add or remove, so the member updates that follow aren't mistaken for manual changes.
+static OWN_ROLE_CHANGES: Lazy<DashMap<(Id<GuildMarker>, Id<UserMarker>), (Instant, HashSet<Id<RoleMarker>>)>> = Lazy::new(DashMap::new);
+
+/// Marks the given roles of a member as changed by Mellow, must be called before the request is made.
+pub fn record_own_role_changes(guild_id: Id<GuildMarker>, member_id: Id<UserMarker>, role_ids: impl IntoIterator<Item = Id<RoleMarker>>) {
+	let mut entry = OWN_ROLE_CHANGES
+		.entry((guild_id, member_id))
+		.or_insert_with(|| (Instant::now(), HashSet::new()));
+	if entry.0.elapsed() > OWN_ROLE_CHANGE_TTL {
+		entry.1.clear();
+	}
+	entry.0 = Instant::now();
+	entry.1.extend(role_ids);
+}
+
+/// Undoes [`record_own_role_changes`] after the request failed, so a later manual change to the same roles isn't mistaken for mellow's own.
+pub fn forget_own_role_changes(guild_id: Id<GuildMarker>, member_id: Id<UserMarker>, role_ids: impl IntoIterator<Item = Id<RoleMarker>>) {
+	take_own_role_changes(guild_id, member_id, &role_ids.into_iter().collect());
+}
+
+// removes the given roles from mellow's own changes, returning the ones that were there.
+fn take_own_role_changes(guild_id: Id<GuildMarker>, member_id: Id<UserMarker>, role_ids: &HashSet<Id<RoleMarker>>) -> HashSet<Id<RoleMarker>> {
+	let key = (guild_id, member_id);
+	let Some(mut entry) = OWN_ROLE_CHANGES.get_mut(&key) else {
+		return HashSet::new();
+	};
+	if entry.0.elapsed() > OWN_ROLE_CHANGE_TTL {
+		drop(entry);
+		OWN_ROLE_CHANGES.remove(&key);
+		return HashSet::new();
+	}
+
+	let taken: HashSet<Id<RoleMarker>> = entry.1
+		.intersection(role_ids)
+		.copied()
+		.collect();
+	entry.1.retain(|x| !taken.contains(x));
+	if entry.1.is_empty() {
+		drop(entry);
+		OWN_ROLE_CHANGES.remove(&key);
+	}
+
+	taken
+}
+
+fn enforced_role_ids(actions: &SyncActionGraph) -> HashSet<Id<RoleMarker>> {
+	actions
+		.actions()
+		.iter()
+		.filter_map(|x| match &x.kind {
+			SyncActionKind::AssignRoles { role_ids, enforced: true, .. } => Some(role_ids),
+			_ => None
+		})
+		.flatten()
+		.copied()
+		.collect()
+}
+
+/// Reverts manual changes to roles managed by enforced sync actions, `old_roles` being the member's roles before the update.
+pub async fn enforce_member_roles(guild_id: Id<GuildMarker>, member_id: Id<UserMarker>, old_roles: &[Id<RoleMarker>], new_roles: &[Id<RoleMarker>]) -> Result<()> {
+	let old_roles: HashSet<Id<RoleMarker>> = old_roles.iter().copied().collect();
+	let new_roles: HashSet<Id<RoleMarker>> = new_roles.iter().copied().collect();
+	let changed_roles: HashSet<Id<RoleMarker>> = old_roles
+		.symmetric_difference(&new_roles)
+		.copied()
+		.collect();
+	if changed_roles.is_empty() {
+		return Ok(());
+	}
+
+	let own_changes = take_own_role_changes(guild_id, member_id, &changed_roles);
+	if own_changes.len() == changed_roles.len() {
+		return Ok(());
+	}
+
+	// a forceful sync of the entire server is about to decide these roles anyway.
+	if is_job_running(guild_id) {
+		return Ok(());
+	}
+
+	let actions = SyncActionGraph::get(guild_id)
+		.await?;
+	let enforced_role_ids = enforced_role_ids(&actions);
+	let manual_changes: HashSet<Id<RoleMarker>> = changed_roles
+		.into_iter()
+		.filter(|x| !own_changes.contains(x) && enforced_role_ids.contains(x))
+		.collect();
+	if manual_changes.is_empty() {
+		return Ok(());
+	}
+
+	// a dry run tells what syncing expects of the member, relative to their roles after the manual change.
+	let result = match CACHE.hakumi.user_by_discord(guild_id, member_id).await? {
+		Some(user_id) => sync_single_user(guild_id, user_id, member_id, SyncingInitiator::Automatic, None, true, false)
+			.await?,
+		None if syncs_unlinked_members(guild_id) => sync_member(guild_id, None, member_id, SyncingInitiator::Automatic, &actions, &ConnectionMetadata::default(), true, false)
+			.await?,
+		_ => return Ok(())
+	};
+	if result.member_status.removed() {
+		return Ok(());
+	}
+
+	let role_changes: Vec<_> = result.role_changes
+		.into_iter()
+		.filter(|x| manual_changes.contains(&x.target_id))
+		.collect();
+	if role_changes.is_empty() {
+		return Ok(());
+	}
+
+	let mut roles: Vec<Id<RoleMarker>> = new_roles
+		.into_iter()
+		.collect();
+	for change in &role_changes {
+		match change.kind {
+			RoleChangeKind::Added => roles.push(change.target_id),
+			RoleChangeKind::Removed => roles.retain(|x| *x != change.target_id)
+		}
+	}
+
+	// looked up before reverting, so mellow's own update isn't the latest entry.
+	let editor_id = find_role_editor(guild_id, member_id)
+		.await;
+
+	record_own_role_changes(guild_id, member_id, role_changes.iter().map(|x| x.target_id));
+	let response = DISCORD_CLIENT
+		.update_guild_member(guild_id, member_id)
+		.roles(&roles)
+		.reason("Reverted a manual change to roles enforced by sync actions")
+		.await;
+	if response.is_err() {
+		forget_own_role_changes(guild_id, member_id, role_changes.iter().map(|x| x.target_id));
+	}
+	response?;
+
+	send_logs(guild_id, vec![ServerLog::EnforcedRoles {
+		user_id: member_id,
+		editor_id,
+		role_changes
+	}]).await
+}
+
+// the audit log is the only place that knows who changed a member's roles, not having access to it isn't worth failing over.
+async fn find_role_editor(guild_id: Id<GuildMarker>, member_id: Id<UserMarker>) -> Option<Id<UserMarker>> {
+	let result: Result<Option<Id<UserMarker>>> = try {
+		DISCORD_CLIENT
+			.audit_log(guild_id)
+			.action_type(AuditLogEventType::MemberRoleUpdate)
+			.limit(AUDIT_LOG_SEARCH_LIMIT)
+			.await?
+			.model()
+			.await?
+			.entries
+			.into_iter()
+			.find(|x| x.target_id.is_some_and(|x| x.cast() == member_id))
+			.and_then(|x| x.user_id)
+	};
+	result.unwrap_or_else(|error| {
+		tracing::debug!(%guild_id, %member_id, %error, "failed to find who changed the member's roles");
+		None
+	})
+}
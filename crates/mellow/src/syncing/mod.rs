@@ -1,20 +1,21 @@
 use async_recursion::async_recursion;
+use chrono::{ DateTime, Duration, Utc };
 use mellow_cache::CACHE;
 use mellow_models::{
+	discord::guild::MemberModel,
 	hakumi::{
 		user::connection::ConnectionKind,
 		visual_scripting::{ DocumentKind, Variable }
 	},
-	mellow::server::sync_action::{
-		CriteriaItem,
-		SyncActionKind,
-		SyncActionModel
+	mellow::server::{
+		sync_action::{ Criteria, CriteriaItem, Reasoning, SyncActionKind, SyncActionModel },
+		SyncHistoryModel
 	},
-	patreon::user_identity::UserIdentityField
+	patreon::user_identity::{ MemberAttributes, PatronStatus, UserIdentityField }
 };
 use mellow_util::{
 	hakuid::{
-		marker::{ ConnectionMarker, DocumentMarker, SyncActionMarker, UserMarker as HakuUserMarker },
+		marker::{ ConnectionMarker, DocumentMarker, SyncActionMarker, SyncJobMarker, UserMarker as HakuUserMarker },
 		HakuId
 	},
 	DISCORD_CLIENT,
@@ -23,26 +24,65 @@ use mellow_util::{
 use rand::{ distributions::Alphanumeric, Rng };
 use serde::{ Serialize, Deserialize };
 use std::{
-	collections::HashMap,
+	collections::{ HashMap, HashSet },
 	pin::Pin
 };
 use twilight_http::request::AuditLogReason;
-use twilight_model::id::{
-	marker::{ GuildMarker, RoleMarker, UserMarker },
-	Id
+use twilight_model::{
+	guild::Permissions,
+	id::{
+		marker::{ GuildMarker, RoleMarker, UserMarker },
+		Id
+	},
+	util::Timestamp
 };
+use twilight_util::snowflake::Snowflake;
 use uuid::Uuid;
 
 use crate::{
-	roblox::get_user_group_roles,
+	github,
+	roblox::get_group_members,
 	server::logging::{ ProfileSyncKind, ServerLog },
 	util::user_server_connections,
-	visual_scripting::{ process_document, variable_from_member },
+	visual_scripting::{ process_document, process_document_in_chain, variable_from_member },
 	Error, Result
 };
 
+pub mod action_graph;
+pub use action_graph::SyncActionGraph;
+
+pub mod automatic;
+use automatic::schedule_unlinked_kick;
+
+pub mod bulk;
+
+pub mod enforcement;
+use enforcement::{ forget_own_role_changes, record_own_role_changes };
+
+pub mod nickname;
+pub use nickname::{ NicknameTemplate, NicknameTemplateError };
+
+pub mod rank_binds;
+
+pub mod removal;
+use removal::remove_member;
+
+pub mod role_hierarchy;
+pub use role_hierarchy::{ RoleHierarchy, UnmanageableReason, UnmanageableRole, UnmoderatableReason };
+
+pub mod rollback;
+
 pub mod sign_ups;
 
+pub mod trace;
+pub use trace::SyncTrace;
+
+const SECONDS_PER_DAY: i64 = 86_400;
+const SECONDS_PER_HOUR: i64 = 3_600;
+
+// https://discord.com/developers/docs/resources/guild#modify-guild-member
+const MAX_TIMEOUT_MINUTES: u32 = 40_320;
+
 #[derive(Debug, Serialize)]
 pub struct SyncMemberResult {
 	#[serde(skip)]
@@ -54,13 +94,52 @@ pub struct SyncMemberResult {
 	pub member_status: MemberStatus,
 	pub profile_changed: bool,
 	pub nickname_change: Option<NicknameChange>,
+	pub timeout_until: Option<DateTime<Utc>>,
 	pub relevant_connections: Vec<HakuId<ConnectionMarker>>,
-	pub user_id: Id<UserMarker>
+	pub document_results: Vec<DocumentResult>,
+	pub user_id: Id<UserMarker>,
+	/// Only produced when asked for, see [`sync_member`].
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub trace: Option<SyncTrace>
+}
+
+/// A [`SyncMemberResult`] along with what it leaves out when serialised on its own, used for previews and sync history.
+#[derive(Serialize)]
+pub struct SyncMemberResultDetails<'a> {
+	#[serde(flatten)]
+	pub result: &'a SyncMemberResult,
+	pub initiator: &'a SyncingInitiator,
+	pub issues: &'a [SyncingIssue],
+	pub member_status: &'a MemberStatus
 }
 
 impl SyncMemberResult {
+	pub fn details(&self) -> SyncMemberResultDetails<'_> {
+		SyncMemberResultDetails {
+			result: self,
+			initiator: &self.initiator,
+			issues: &self.issues,
+			member_status: &self.member_status
+		}
+	}
+
 	pub fn create_log(&self) -> Option<ServerLog> {
-		if self.profile_changed || self.member_status.removed() {
+		let unmanageable_roles: Vec<UnmanageableRole> = self.issues
+			.iter()
+			.filter_map(|x| match x {
+				SyncingIssue::UnmanageableRoles(roles) => Some(roles.clone()),
+				_ => None
+			})
+			.flatten()
+			.collect();
+		let unmoderatable_reasons: Vec<UnmoderatableReason> = self.issues
+			.iter()
+			.filter_map(|x| match x {
+				SyncingIssue::UnmoderatableMember(reason) => Some(reason.clone()),
+				_ => None
+			})
+			.collect();
+		if self.profile_changed || self.member_status.removed() || !self.document_results.is_empty() || !unmanageable_roles.is_empty() || !unmoderatable_reasons.is_empty() {
 			Some(ServerLog::ServerProfileSync {
 				kind: match self.member_status {
 					MemberStatus::Ok => ProfileSyncKind::Default,
@@ -71,24 +150,50 @@ impl SyncMemberResult {
 				user_id: self.user_id,
 				role_changes: self.role_changes.clone(),
 				nickname_change: self.nickname_change.clone(),
-				relevant_connections: self.relevant_connections.clone()
+				timeout_until: self.timeout_until,
+				relevant_connections: self.relevant_connections.clone(),
+				document_results: self.document_results.clone(),
+				unmanageable_roles,
+				unmoderatable_reasons,
+				undelivered_removal_message: self.issues
+					.iter()
+					.any(|x| matches!(x, SyncingIssue::UndeliveredRemovalMessage))
 			})
 		} else { None }
 	}
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "kind", content = "data", rename_all = "snake_case")]
 pub enum SyncingInitiator {
 	Automatic,
 	ForcedBy(Id<UserMarker>),
+	ForcedBySyncJob {
+		initiator_id: Id<UserMarker>,
+		job_id: HakuId<SyncJobMarker>
+	},
 	Manual,
-	VisualScriptingDocument(HakuId<DocumentMarker>)
+	VisualScriptingDocument {
+		document_id: HakuId<DocumentMarker>,
+		/// The documents whose syncs led to this one, a document in here is never executed again.
+		#[serde(skip)]
+		document_chain: Vec<HakuId<DocumentMarker>>
+	}
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "kind", content = "data", rename_all = "snake_case")]
 pub enum SyncingIssue {
+	InvalidNicknameTemplate(NicknameTemplateError),
 	MissingConnections,
-	MissingOAuthAuthorisation(ConnectionKind)
+	MissingOAuthAuthorisation(ConnectionKind),
+	ServiceUnavailable(ConnectionKind),
+	/// Names of sync actions that depend on each other, which were skipped.
+	SyncActionCycle(Vec<String>),
+	UndeliveredRemovalMessage,
+	UnmanageableRoles(Vec<UnmanageableRole>),
+	/// A ban, kick or timeout that was skipped, as Mellow isn't able to moderate the member.
+	UnmoderatableMember(UnmoderatableReason)
 }
 
 impl SyncingIssue {
@@ -103,6 +208,8 @@ impl SyncingIssue {
 
 	pub async fn display(&self, guild_id: Id<GuildMarker>, user_id: HakuId<HakuUserMarker>, website_token: &str) -> Result<String> {
 		Ok(match self {
+			Self::InvalidNicknameTemplate(error) =>
+				format!("This server's nickname template is invalid ({error}), a server manager can fix it [here](<https://hakumi.cafe/mellow/server/{guild_id}/settings/syncing>)."),
 			Self::MissingConnections =>
 				format!("You haven't given this server access to all connections yet, fix that [here](<https://hakumi.cafe/mellow/server/{guild_id}/user_settings?mt={website_token}>)!"),
 			Self::MissingOAuthAuthorisation(connection_kind) => {
@@ -126,7 +233,22 @@ impl SyncingIssue {
 					.await?;
 
 				format!("Your {connection_kind:?} connection was invalidated, please [reconnect it](<https://www.patreon.com/oauth2/authorize?client_id=BaKp_8PIeBxx0cfJoEEaVxVQMxD3c_IUFS_qCSu5gNFnXLL5c4Qw4YMPtgMJG-n9&redirect_uri=https%3A%2F%2Fapi-new.hakumi.cafe%2Fv1%2Fconnection_callback%2F4&scope=identity%20identity.memberships&response_type=code&state=m1-{token}>).")
-			}
+			},
+			Self::ServiceUnavailable(connection_kind) =>
+				format!("{connection_kind:?} couldn't be reached right now, so anything depending on it was left as-is. Try again in a few minutes!"),
+			Self::SyncActionCycle(display_names) =>
+				format!("Some of this server's sync actions depend on each other in a loop, so they were skipped ({}), a server manager can fix this [here](<https://hakumi.cafe/mellow/server/{guild_id}/syncing/actions>).", display_names.join(", ")),
+			Self::UndeliveredRemovalMessage =>
+				"The member couldn't be notified before being removed, they likely have direct messages closed.".into(),
+			Self::UnmanageableRoles(roles) =>
+				format!("Some roles were skipped, as Mellow isn't able to manage them, a server manager can fix this in the server's role settings.\n{}", roles
+					.iter()
+					.map(|x| format!("* **{}** — {}", x.display_name, x.reason.display()))
+					.collect::<Vec<String>>()
+					.join("\n")
+				),
+			Self::UnmoderatableMember(reason) =>
+				format!("A sync action tried to moderate this member, but Mellow isn't able to, as {}.", reason.display())
 		})
 	}
 }
@@ -147,11 +269,18 @@ pub enum RoleChangeKind {
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct NicknameChange(pub Option<String>, pub Option<String>);
 
+#[derive(Clone, Debug, Serialize)]
+pub struct DocumentResult {
+	pub document_name: String,
+	pub items: Vec<String>
+}
+
 #[derive(Debug)]
 pub struct PatreonPledge {
 	pub campaign_id: String,
 	pub connection_id: HakuId<ConnectionMarker>,
 	pub tiers: Vec<String>,
+	pub attributes: MemberAttributes,
 	pub user_id: Uuid,
 }
 
@@ -163,14 +292,33 @@ pub struct RobloxMembership {
 	pub group_id: u64
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub enum GitHubRelationKind {
+	OrganizationMember(String),
+	RepositoryContributor(String, String),
+	Sponsor(String)
+}
+
+/// A relation between a GitHub user and an organisation, repository or sponsorable account.
+/// Names are stored in lowercase, as GitHub treats them case-insensitively.
 #[derive(Debug)]
+pub struct GitHubRelation {
+	pub kind: GitHubRelationKind,
+	pub user_id: String
+}
+
+#[derive(Debug, Default)]
 pub struct ConnectionMetadata {
 	pub issues: Vec<SyncingIssue>,
+	// issues that only concern some of the users, such as a service failing to look them up.
+	pub user_issues: HashMap<HakuId<HakuUserMarker>, Vec<SyncingIssue>>,
+	pub github_relations: Vec<GitHubRelation>,
 	pub patreon_pledges: Vec<PatreonPledge>,
 	pub roblox_memberships: Vec<RobloxMembership>
 }
 
-#[derive(Debug)]
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum MemberStatus {
 	Ok,
 	Banned,
@@ -183,105 +331,231 @@ impl MemberStatus {
 	}
 }
 
-pub async fn get_connection_metadata(guild_id: Id<GuildMarker>, user_ids: &Vec<HakuId<HakuUserMarker>>) -> Result<ConnectionMetadata> {
+pub async fn get_connection_metadata(guild_id: Id<GuildMarker>, user_ids: &Vec<HakuId<HakuUserMarker>>, actions: &SyncActionGraph) -> Result<ConnectionMetadata> {
 	let mut issues: Vec<SyncingIssue> = Vec::new();
+	let mut user_issues: HashMap<HakuId<HakuUserMarker>, Vec<SyncingIssue>> = HashMap::new();
+	let mut github_relations: Vec<GitHubRelation> = Vec::new();
+	let mut github_targets: Vec<GitHubRelationKind> = Vec::new();
 	let mut patreon_pledges: Vec<PatreonPledge> = Vec::new();
 	let mut roblox_memberships: Vec<RobloxMembership> = Vec::new();
 	let mut group_ids: Vec<u64> = Vec::new();
+	let mut needs_patreon = false;
 
-	let action_ids = CACHE
-		.mellow
-		.server_sync_actions(guild_id)
-		.await?;
-
-	// TODO: no cloning!!!!!!!!!!!!!!!!!!!!!!!!!!!!!
-	let actions: Vec<_> = CACHE
-		.mellow
-		.sync_actions(&action_ids)
-		.await?
-		.into_iter()
-		.map(|x| x.clone())
-		.collect();
-	for action in actions {
-		for criteria_item in action.criteria.items {
+	for action in actions.actions() {
+		for criteria_item in action.criteria.leaf_items() {
 			match criteria_item {
+				CriteriaItem::GitHubOrganizationMembership { .. } |
+				CriteriaItem::GitHubRepositoryContributor { .. } |
+				CriteriaItem::GitHubSponsorship { .. } => {
+					let target = match criteria_item {
+						CriteriaItem::GitHubOrganizationMembership { organization } =>
+							GitHubRelationKind::OrganizationMember(organization.to_lowercase()),
+						CriteriaItem::GitHubRepositoryContributor { owner, repository } =>
+							GitHubRelationKind::RepositoryContributor(owner.to_lowercase(), repository.to_lowercase()),
+						CriteriaItem::GitHubSponsorship { account } =>
+							GitHubRelationKind::Sponsor(account.to_lowercase()),
+						_ => unreachable!()
+					};
+					if !github_targets.contains(&target) {
+						github_targets.push(target);
+					}
+				},
 				CriteriaItem::RobloxGroupMembership { group_id } |
 				CriteriaItem::RobloxGroupMembershipRole { group_id, .. } |
 				CriteriaItem::RobloxGroupMembershipRoleRankInRange { group_id, .. } => {
-					if !group_ids.contains(&group_id) {
-						group_ids.push(group_id);
+					if !group_ids.contains(group_id) {
+						group_ids.push(*group_id);
 					}
 				},
-				CriteriaItem::PatreonCampaignTierSubscription { .. } => {
-					for user_id in user_ids {
-						let connections = user_server_connections(guild_id, *user_id)
-							.await?;
-						if let Some(connection) = connections.into_iter().find(|x| x.is_patreon()) {
-							let connection_id = connection.id;
-							drop(connection);
-							
-							let user_identity = CACHE
-								.patreon
-								.user_identity(connection_id)
-								.await?;
-							if let Some(user_identity) = user_identity {
-								if let Some(included) = &user_identity.included {
-									for membership in included {
-										if let UserIdentityField::Member(member) = membership {
-											patreon_pledges.push(PatreonPledge {
-												campaign_id: member.relationships.campaign.data.id.clone(),
-												connection_id,
-												tiers: member.relationships.currently_entitled_tiers.data.0.iter().map(|x| x.id.clone()).collect(),
-												user_id: user_id.value
-											});
-										}
-									}
-								}
-							} else if !issues.iter().any(|x| matches!(x, SyncingIssue::MissingOAuthAuthorisation(ConnectionKind::Patreon))) {
-								issues.push(SyncingIssue::MissingOAuthAuthorisation(ConnectionKind::Patreon));
+				CriteriaItem::PatreonCampaignTierSubscription { .. } |
+				CriteriaItem::PatreonCampaignPatronStatus { .. } |
+				CriteriaItem::PatreonCampaignPledgeAmount { .. } |
+				CriteriaItem::PatreonCampaignLifetimeSupport { .. } |
+				CriteriaItem::PatreonCampaignPledgeDuration { .. } => needs_patreon = true,
+				_ => {}
+			}
+		}
+	}
+
+	if needs_patreon {
+		for user_id in user_ids {
+			let connections = user_server_connections(guild_id, *user_id)
+				.await?;
+			let connection_ids: Vec<HakuId<ConnectionMarker>> = connections
+				.into_iter()
+				.filter(|x| x.is_patreon())
+				.map(|x| x.id)
+				.collect();
+			for connection_id in connection_ids {
+				let user_identity = CACHE
+					.patreon
+					.user_identity(connection_id)
+					.await?;
+				if let Some(user_identity) = user_identity {
+					if let Some(included) = &user_identity.included {
+						for membership in included {
+							if let UserIdentityField::Member(member) = membership {
+								patreon_pledges.push(PatreonPledge {
+									campaign_id: member.relationships.campaign.data.id.clone(),
+									connection_id,
+									tiers: member.relationships.currently_entitled_tiers.data.0.iter().map(|x| x.id.clone()).collect(),
+									attributes: member.attributes.clone(),
+									user_id: user_id.value
+								});
 							}
 						}
 					}
-				},
-				_ => {}
+				} else if !issues.iter().any(|x| matches!(x, SyncingIssue::MissingOAuthAuthorisation(ConnectionKind::Patreon))) {
+					issues.push(SyncingIssue::MissingOAuthAuthorisation(ConnectionKind::Patreon));
+				}
 			}
 		}
 	}
 
 	if !group_ids.is_empty() {
-		//let roblox_ids: Vec<String> = users.iter().flat_map(|x| x.user.connections.iter().filter(|x| matches!(x.connection.kind, ConnectionKind::Roblox)).map(|x| format!("users/{}", x.connection.sub)).collect::<Vec<String>>()).collect();
-		//let items = get_group_memberships("-", Some(format!("user in ['{}']", roblox_ids.join("','")))).await;
 		let mut ids: Vec<String> = vec![];
+		let mut owners: Vec<(String, HakuId<HakuUserMarker>)> = vec![];
 		for user_id in user_ids {
 			let connections = user_server_connections(guild_id, *user_id)
 				.await?;
-			ids.extend(
-				connections
+			for connection in connections {
+				if connection.is_roblox() {
+					if !ids.contains(&connection.sub) {
+						ids.push(connection.sub.clone());
+					}
+					owners.push((connection.sub.clone(), *user_id));
+				}
+			}
+		}
+
+		// an outage shouldn't fail the whole sync, actions depending on roblox are skipped instead.
+		match get_group_members(&group_ids, &ids).await {
+			Ok(members) => {
+				// users that failed to be looked up have their roblox actions skipped, rather than treated as not being a member.
+				for (roblox_id, user_id) in owners {
+					if members.failed_user_ids.contains(&roblox_id) {
+						let user_issues = user_issues
+							.entry(user_id)
+							.or_insert_with(Vec::new);
+						if user_issues.is_empty() {
+							user_issues.push(SyncingIssue::ServiceUnavailable(ConnectionKind::Roblox));
+						}
+					}
+				}
+				roblox_memberships.extend(members
+					.members
 					.into_iter()
-					.filter(|x| x.is_roblox())
-					.map(|x| x.sub.clone())
-			);
+					.map(|x| RobloxMembership {
+						rank: x.rank,
+						role: x.role_id,
+						user_id: x.user_id,
+						group_id: x.group_id
+					})
+				);
+			},
+			Err(error) => {
+				tracing::warn!(%error, "roblox group membership lookup failed");
+				issues.push(SyncingIssue::ServiceUnavailable(ConnectionKind::Roblox));
+			}
 		}
-		for id in ids {
-			let roles = get_user_group_roles(&id).await?;
-			for role in roles {
-				roblox_memberships.push(RobloxMembership {
-					role: role.role.id,
-					rank: role.role.rank,
-					user_id: id.clone(),
-					group_id: role.group.id
-				});
+	}
+
+	if !github_targets.is_empty() {
+		let mut accounts: Vec<(String, Option<String>)> = vec![];
+		for user_id in user_ids {
+			let connections = user_server_connections(guild_id, *user_id)
+				.await?;
+			for connection in connections {
+				if connection.kind == ConnectionKind::GitHub && !accounts.iter().any(|x| x.0 == connection.sub) {
+					accounts.push((connection.sub.clone(), connection.username.clone()));
+				}
 			}
 		}
+
+		github_relations.extend(get_github_relations_or_flag(github_targets, &accounts, &mut issues)
+			.await
+		);
 	}
 
 	Ok(ConnectionMetadata {
 		issues,
+		user_issues,
+		github_relations,
 		patreon_pledges,
 		roblox_memberships
 	})
 }
 
+// github being unreachable leaves every github item undetermined, rather than failing the sync.
+async fn get_github_relations_or_flag(targets: Vec<GitHubRelationKind>, accounts: &[(String, Option<String>)], issues: &mut Vec<SyncingIssue>) -> Vec<GitHubRelation> {
+	match get_github_relations(targets, accounts).await {
+		Ok(relations) => relations,
+		Err(error) => {
+			tracing::warn!(%error, "github lookup failed");
+			issues.push(SyncingIssue::ServiceUnavailable(ConnectionKind::GitHub));
+			vec![]
+		}
+	}
+}
+
+// accounts are pairs of github user ids and usernames.
+async fn get_github_relations(targets: Vec<GitHubRelationKind>, accounts: &[(String, Option<String>)]) -> Result<Vec<GitHubRelation>> {
+	let mut relations: Vec<GitHubRelation> = vec![];
+	if accounts.is_empty() {
+		return Ok(relations);
+	}
+
+	let usernames: Vec<String> = accounts
+		.iter()
+		.filter_map(|x| x.1.clone())
+		.collect();
+	for target in targets {
+		// every target is looked up for all accounts at once, rather than one request per account.
+		let (contributors, related_usernames) = match &target {
+			GitHubRelationKind::RepositoryContributor(owner, repository) =>
+				(github::get_repository_contributors(owner, repository).await?, vec![]),
+			GitHubRelationKind::OrganizationMember(organization) =>
+				(vec![], github::get_public_organization_members(organization, &usernames).await?),
+			GitHubRelationKind::Sponsor(account) =>
+				(vec![], github::get_sponsors(account, &usernames).await?)
+		};
+		for (user_id, username) in accounts {
+			let is_related = match (&target, username) {
+				(GitHubRelationKind::RepositoryContributor(..), _) =>
+					contributors.iter().any(|x| &x.id.to_string() == user_id),
+				(_, Some(username)) => related_usernames.contains(username),
+				_ => false
+			};
+			if is_related {
+				relations.push(GitHubRelation {
+					kind: target.clone(),
+					user_id: user_id.clone()
+				});
+			}
+		}
+	}
+
+	Ok(relations)
+}
+
+fn flag_unmanageable_role(unmanageable_roles: &mut Vec<UnmanageableRole>, guild_id: Id<GuildMarker>, role_id: Id<RoleMarker>, reason: UnmanageableReason) {
+	if !unmanageable_roles.iter().any(|x| x.role_id == role_id) {
+		unmanageable_roles.push(UnmanageableRole {
+			role_id,
+			display_name: get_role_name(guild_id, role_id),
+			reason
+		});
+	}
+}
+
+// discord doesn't allow administrators to be timed out.
+fn is_administrator(guild_id: Id<GuildMarker>, member: &MemberModel) -> bool {
+	member.roles
+		.iter()
+		.filter_map(|x| CACHE.discord.role(guild_id, *x))
+		.any(|x| x.permissions.contains(Permissions::ADMINISTRATOR))
+}
+
 fn get_role_name(guild_id: Id<GuildMarker>, role_id: Id<RoleMarker>) -> String {
 	CACHE
 		.discord
@@ -292,57 +566,171 @@ fn get_role_name(guild_id: Id<GuildMarker>, role_id: Id<RoleMarker>) -> String {
 // async_recursion required due to a cycle error caused by visual scripting
 #[async_recursion]
 #[tracing::instrument(level = "trace")]
-pub async fn sync_single_user(guild_id: Id<GuildMarker>, user_id: HakuId<HakuUserMarker>, member_id: Id<UserMarker>, initiator: SyncingInitiator, connection_metadata: Option<ConnectionMetadata>) -> Result<SyncMemberResult> {
+pub async fn sync_single_user(guild_id: Id<GuildMarker>, user_id: HakuId<HakuUserMarker>, member_id: Id<UserMarker>, initiator: SyncingInitiator, connection_metadata: Option<ConnectionMetadata>, is_dry_run: bool, with_trace: bool) -> Result<SyncMemberResult> {
+	let actions = SyncActionGraph::get(guild_id)
+		.await?;
 	let metadata = match connection_metadata {
 		Some(x) => x,
-		None => get_connection_metadata(guild_id, &vec![user_id]).await?
+		None => get_connection_metadata(guild_id, &vec![user_id], &actions).await?
 	};
-	sync_member(guild_id, Some(user_id), member_id, initiator, &metadata).await
+	sync_member(guild_id, Some(user_id), member_id, initiator, &actions, &metadata, is_dry_run, with_trace).await
 }
 
+/// Evaluates every sync action of the server against the given member, and applies the outcome.
+/// When `is_dry_run` is set, nothing is sent to Discord, the returned result describes what *would* have happened.
+/// When `with_trace` is set, the result also explains how each action was evaluated.
+#[allow(clippy::too_many_arguments)]
 #[tracing::instrument(level = "trace")]
-pub async fn sync_member(guild_id: Id<GuildMarker>, user_id: Option<HakuId<HakuUserMarker>>, member_id: Id<UserMarker>, initiator: SyncingInitiator, connection_metadata: &ConnectionMetadata) -> Result<SyncMemberResult> {
-	let roles = CACHE
+pub async fn sync_member(guild_id: Id<GuildMarker>, user_id: Option<HakuId<HakuUserMarker>>, member_id: Id<UserMarker>, initiator: SyncingInitiator, actions: &SyncActionGraph, connection_metadata: &ConnectionMetadata, is_dry_run: bool, with_trace: bool) -> Result<SyncMemberResult> {
+	let result = apply_sync_actions(guild_id, user_id, member_id, initiator, actions, connection_metadata, is_dry_run, with_trace)
+		.await?;
+	// syncs that left the member as they were aren't worth keeping, unless something got in the way.
+	if !is_dry_run && (result.profile_changed || result.member_status.removed() || !result.issues.is_empty()) {
+		// history is only informational, failing to store it shouldn't fail the sync itself.
+		if let Err(error) = record_sync_history(guild_id, user_id, &result).await {
+			tracing::warn!(%guild_id, %member_id, %error, "failed to record sync history");
+		}
+	}
+
+	Ok(result)
+}
+
+async fn record_sync_history(guild_id: Id<GuildMarker>, user_id: Option<HakuId<HakuUserMarker>>, result: &SyncMemberResult) -> Result<()> {
+	let sync_job_id = match result.initiator {
+		SyncingInitiator::ForcedBySyncJob { job_id, .. } => Some(job_id),
+		_ => None
+	};
+	let value = serde_json::to_value(result.details())?;
+	SyncHistoryModel::create(guild_id, result.user_id, user_id, sync_job_id, result.profile_changed, value)
+		.await?;
+
+	Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn apply_sync_actions(guild_id: Id<GuildMarker>, user_id: Option<HakuId<HakuUserMarker>>, member_id: Id<UserMarker>, initiator: SyncingInitiator, actions: &SyncActionGraph, connection_metadata: &ConnectionMetadata, is_dry_run: bool, with_trace: bool) -> Result<SyncMemberResult> {
+	let member = CACHE
 		.discord
 		.member(guild_id, member_id)
 		.await?
-		.roles
 		.clone();
-	
+	let roles = member.roles.clone();
+
 	let mut issues = connection_metadata.issues.clone();
+	if let Some(user_id) = user_id && let Some(user_issues) = connection_metadata.user_issues.get(&user_id) {
+		issues.extend(user_issues.iter().cloned());
+	}
+	for cycle in actions.cycles() {
+		issues.push(SyncingIssue::SyncActionCycle(cycle
+			.iter()
+			.map(|x| x.display_name.clone())
+			.collect()
+		));
+	}
 	let mut new_roles = roles.clone();
 	let mut role_changes: Vec<RoleChange> = vec![];
 	let mut member_status = MemberStatus::Ok;
-	let mut criteria_cache: HashMap<(HakuId<SyncActionMarker>, usize), bool> = HashMap::new();
+	let mut criteria_cache: HashMap<(HakuId<SyncActionMarker>, Vec<usize>), bool> = HashMap::new();
 	let mut used_connections: Vec<HakuId<ConnectionMarker>> = vec![];
+	let mut document_results: Vec<DocumentResult> = vec![];
+	// documents run once the member has been updated, so whatever they change isn't overwritten by the sync.
+	let mut pending_documents: Vec<(HakuId<DocumentMarker>, bool)> = vec![];
+	let mut nickname_override: Option<String> = None;
+	let mut timeout: Option<(DateTime<Utc>, String)> = None;
 
 	let server = CACHE
 		.mellow
 		.server(guild_id)
 		.ok_or(Error::ServerNotFound)?;
 	let default_nickname = server.default_nickname.clone();
+	let unlinked_member_policy = server.unlinked_member_policy.clone();
+	drop(server);
 
-	let action_ids = CACHE
-		.mellow
-		.server_sync_actions(guild_id)
+	let guild_owner_id = CACHE
+		.discord
+		.guild(guild_id)
+		.await?
+		.owner_id;
+	let role_hierarchy = RoleHierarchy::get(guild_id)
 		.await?;
+	let mut unmanageable_roles: Vec<UnmanageableRole> = vec![];
 
-	// TODO: no cloning!!!!!!!!!!!!!!!!!!!!!!!!!!!!!
-	let actions: Vec<_> = CACHE
-		.mellow
-		.sync_actions(&action_ids)
-		.await?
-		.into_iter()
-		.map(|x| x.clone())
+	let unavailable_services: Vec<&ConnectionKind> = issues
+		.iter()
+		.filter_map(|x| match x {
+			SyncingIssue::ServiceUnavailable(connection_kind) => Some(connection_kind),
+			_ => None
+		})
 		.collect();
-	for action in actions.iter() {
-		let met = member_meets_action_criteria(guild_id, user_id, action, &actions, connection_metadata, &mut criteria_cache, &mut used_connections)
+
+	// actions that depend on an unavailable service can't be trusted either way, so they aren't applied.
+	let mut action_results: HashMap<HakuId<SyncActionMarker>, bool> = HashMap::new();
+	let mut undetermined_actions: HashSet<HakuId<SyncActionMarker>> = HashSet::new();
+	for action in actions.evaluation_order() {
+		let met = member_meets_action_criteria(guild_id, user_id, &member, action, &action_results, connection_metadata, &mut criteria_cache, &mut used_connections)
 			.await?;
+		action_results.insert(action.id, met);
+
+		if action.criteria.leaf_items().into_iter().any(|item| match item {
+			CriteriaItem::HakumiUserConnection { .. } => false,
+			CriteriaItem::MellowServerSyncingActions { action_ids, .. } =>
+				action_ids.iter().any(|x| undetermined_actions.contains(x)),
+			_ => item
+				.relevant_connection()
+				.is_some_and(|x| unavailable_services.contains(&&x))
+		}) {
+			undetermined_actions.insert(action.id);
+		}
+	}
+
+	// the winner of a role group can't be known while an action ranked above it is undetermined.
+	let mut role_group_winners: HashMap<&str, HakuId<SyncActionMarker>> = HashMap::new();
+	let mut undetermined_role_groups: HashSet<&str> = HashSet::new();
+	let mut winning_role_ids: HashSet<Id<RoleMarker>> = HashSet::new();
+	// roles assigned by met actions, which the unlinked member policy never takes away.
+	let mut assigned_role_ids: HashSet<Id<RoleMarker>> = HashSet::new();
+	for action in actions.actions() {
+		if
+			let SyncActionKind::AssignRoles { role_ids, .. } = &action.kind &&
+			let Some(role_group) = action.role_group.as_deref() &&
+			!role_group_winners.contains_key(role_group) &&
+			!undetermined_role_groups.contains(role_group)
+		{
+			if undetermined_actions.contains(&action.id) {
+				undetermined_role_groups.insert(role_group);
+			} else if action_results[&action.id] {
+				role_group_winners.insert(role_group, action.id);
+				winning_role_ids.extend(role_ids);
+			}
+		}
+	}
+
+	let mut trace = if with_trace {
+		Some(SyncTrace::new(guild_id, user_id, actions, &action_results, &criteria_cache, &undetermined_actions, &role_group_winners, &undetermined_role_groups, connection_metadata).await?)
+	} else { None };
+
+	for action in actions.actions() {
+		if undetermined_actions.contains(&action.id) || action.role_group.as_deref().is_some_and(|x| undetermined_role_groups.contains(x)) {
+			continue;
+		}
+
+		let met = action_results[&action.id];
 		match &action.kind {
-			SyncActionKind::AssignRoles { role_ids, can_remove } => {
+			SyncActionKind::AssignRoles { role_ids, can_remove, .. } => {
+				// actions in a role group that didn't win always give up their roles, unless the winner assigns them too.
+				let (met, can_remove) = match action.role_group.as_deref() {
+					Some(role_group) => (role_group_winners.get(role_group) == Some(&action.id), true),
+					None => (met, *can_remove)
+				};
 				if met {
-					if !role_ids.iter().all(|x| new_roles.iter().any(|e| e == x)) {
-						for role_id in role_ids.iter().filter(|x| !roles.iter().any(|e| &e == x)) {
+					assigned_role_ids.extend(role_ids);
+					for role_id in role_ids {
+						if !new_roles.contains(role_id) {
+							if let Some(reason) = role_hierarchy.check(guild_id, *role_id) {
+								flag_unmanageable_role(&mut unmanageable_roles, guild_id, *role_id, reason);
+								continue;
+							}
+
 							new_roles.push(*role_id);
 							role_changes.push(RoleChange {
 								kind: RoleChangeKind::Added,
@@ -351,38 +739,99 @@ pub async fn sync_member(guild_id: Id<GuildMarker>, user_id: Option<HakuId<HakuU
 							});
 						}
 					}
-				} else if *can_remove {
-					let filtered: Vec<Id<RoleMarker>> = new_roles.iter().filter(|x| !role_ids.contains(x)).cloned().collect();
-					if !new_roles.iter().all(|x| filtered.contains(x)) {
-						for role_id in role_ids {
-							if new_roles.contains(role_id) {
-								role_changes.push(RoleChange {
-									kind: RoleChangeKind::Removed,
-									target_id: *role_id,
-									display_name: get_role_name(guild_id, *role_id)
-								});
+				} else if can_remove {
+					for role_id in role_ids {
+						if new_roles.contains(role_id) && !(action.role_group.is_some() && winning_role_ids.contains(role_id)) {
+							if let Some(reason) = role_hierarchy.check(guild_id, *role_id) {
+								flag_unmanageable_role(&mut unmanageable_roles, guild_id, *role_id, reason);
+								continue;
 							}
+
+							new_roles.retain(|x| x != role_id);
+							role_changes.push(RoleChange {
+								kind: RoleChangeKind::Removed,
+								target_id: *role_id,
+								display_name: get_role_name(guild_id, *role_id)
+							});
 						}
-						new_roles = filtered;
+					}
+				}
+			},
+			SyncActionKind::RemoveRolesExcept { role_ids } => if met {
+				for role_id in new_roles.clone() {
+					if role_ids.contains(&role_id) {
+						continue;
+					}
+					match role_hierarchy.check(guild_id, role_id) {
+						// integration roles can't be removed by anyone, so they're expected to stay.
+						Some(UnmanageableReason::Managed) => continue,
+						Some(reason) => {
+							flag_unmanageable_role(&mut unmanageable_roles, guild_id, role_id, reason);
+							continue;
+						},
+						None => ()
+					}
+
+					new_roles.retain(|x| *x != role_id);
+					// a role assigned by an earlier action is simply not assigned anymore.
+					if let Some(index) = role_changes.iter().position(|x| x.target_id == role_id && matches!(x.kind, RoleChangeKind::Added)) {
+						role_changes.remove(index);
+					} else {
+						role_changes.push(RoleChange {
+							kind: RoleChangeKind::Removed,
+							target_id: role_id,
+							display_name: get_role_name(guild_id, role_id)
+						});
+					}
+				}
+			},
+			SyncActionKind::SetNickname { template } => if met && nickname_override.is_none() {
+				nickname_override = Some(template.clone());
+			},
+			SyncActionKind::TimeoutMember { duration_minutes, reason } => if met && timeout.is_none() && !is_administrator(guild_id, &member) {
+				// a timeout that's still in place is left to run out, rather than being extended by every sync that follows.
+				let now = Utc::now();
+				if member.communication_disabled_until.is_none_or(|x| x.as_secs() <= now.timestamp()) {
+					// checked beforehand, as the timeout is sent along with every other change to the member.
+					match role_hierarchy.check_member(guild_id, member_id, &roles, guild_owner_id, Permissions::MODERATE_MEMBERS) {
+						Some(unmoderatable_reason) => issues.push(SyncingIssue::UnmoderatableMember(unmoderatable_reason)),
+						None => timeout = Some((
+							now + Duration::minutes((*duration_minutes).min(MAX_TIMEOUT_MINUTES) as i64),
+							format!("Met criteria of {} — {}", action.display_name, reason.as_deref().unwrap_or("No reason"))
+						))
 					}
 				}
 			},
 			SyncActionKind::BanMember(reasoning) => if met {
-				// TODO: notify user via direct messages
+				if let Some(reason) = role_hierarchy.check_member(guild_id, member_id, &roles, guild_owner_id, Permissions::BAN_MEMBERS) {
+					issues.push(SyncingIssue::UnmoderatableMember(reason));
+					continue;
+				}
+
 				member_status = MemberStatus::Banned;
-				DISCORD_CLIENT
-					.create_ban(guild_id, member_id)
-					.reason(&format!("Met criteria of {} — {}", action.display_name, reasoning.reason.as_ref().unwrap_or(&"No reason".into())))
-					.await?;
+				// members with closed direct messages are still removed, the failure only ends up in the log.
+				if !is_dry_run && remove_member(guild_id, member_id, &member_status, reasoning, &format!("Met criteria of {} — {}", action.display_name, reasoning.reason.as_deref().unwrap_or("No reason"))).await? {
+					issues.push(SyncingIssue::UndeliveredRemovalMessage);
+				}
+				if let Some(trace) = &mut trace {
+					trace.halted_by = Some(action.id);
+				}
 				break;
 			},
 			SyncActionKind::KickMember(reasoning) => if met {
-				// TODO: notify user via direct messages
+				if let Some(reason) = role_hierarchy.check_member(guild_id, member_id, &roles, guild_owner_id, Permissions::KICK_MEMBERS) {
+					issues.push(SyncingIssue::UnmoderatableMember(reason));
+					continue;
+				}
+
 				member_status = MemberStatus::Kicked;
-				DISCORD_CLIENT
-					.remove_guild_member(guild_id, member_id)
-					.reason(&format!("Met criteria of {} — {}", action.display_name, reasoning.reason.as_ref().unwrap_or(&"No reason".into())))
-					.await?;
+				// members with closed direct messages are still removed, the failure only ends up in the log.
+				if !is_dry_run && remove_member(guild_id, member_id, &member_status, reasoning, &format!("Met criteria of {} — {}", action.display_name, reasoning.reason.as_deref().unwrap_or("No reason"))).await? {
+					issues.push(SyncingIssue::UndeliveredRemovalMessage);
+				}
+				if let Some(trace) = &mut trace {
+					trace.halted_by = Some(action.id);
+				}
 				break;
 			},
 			SyncActionKind::ControlFlowCancel(_reasoning) => return Ok(SyncMemberResult {
@@ -392,34 +841,113 @@ pub async fn sync_member(guild_id: Id<GuildMarker>, user_id: Option<HakuId<HakuU
 				member_status,
 				profile_changed: false,
 				nickname_change: None,
+				timeout_until: None,
 				relevant_connections: vec![],
-				user_id: member_id
+				document_results,
+				user_id: member_id,
+				trace: trace.map(|x| SyncTrace {
+					halted_by: Some(action.id),
+					..x
+				})
 			}),
-			SyncActionKind::ExecuteDocument => unimplemented!()
+			SyncActionKind::ExecuteDocument { document_id } => pending_documents.push((*document_id, met))
 		};
 	}
 
-	let target_nickname = match default_nickname {
-		Some(t) => if let Some(user_id) = user_id {
-			let connections = user_server_connections(guild_id, user_id)
-				.await?;
-			match t.as_str() {
-				"{roblox_username}" =>
-					connections.into_iter().find(|x| x.is_roblox()).and_then(|x| x.username.clone()),
-				"{roblox_display_name}" =>
-					connections.into_iter().find(|x| x.is_roblox()).and_then(|x| x.display_name.clone()),
-				_ => None
+	if let Some(policy) = &unlinked_member_policy && !member_status.removed() {
+		if user_id.is_none() {
+			for role_id in &policy.role_ids {
+				if !new_roles.contains(role_id) {
+					if let Some(reason) = role_hierarchy.check(guild_id, *role_id) {
+						flag_unmanageable_role(&mut unmanageable_roles, guild_id, *role_id, reason);
+						continue;
+					}
+
+					new_roles.push(*role_id);
+					role_changes.push(RoleChange {
+						kind: RoleChangeKind::Added,
+						target_id: *role_id,
+						display_name: get_role_name(guild_id, *role_id)
+					});
+				}
 			}
-		} else { None },
+			for role_id in &policy.removed_role_ids {
+				if new_roles.contains(role_id) && !assigned_role_ids.contains(role_id) {
+					if let Some(reason) = role_hierarchy.check(guild_id, *role_id) {
+						flag_unmanageable_role(&mut unmanageable_roles, guild_id, *role_id, reason);
+						continue;
+					}
+
+					new_roles.retain(|x| x != role_id);
+					role_changes.push(RoleChange {
+						kind: RoleChangeKind::Removed,
+						target_id: *role_id,
+						display_name: get_role_name(guild_id, *role_id)
+					});
+				}
+			}
+			if nickname_override.is_none() {
+				nickname_override = policy.nickname.clone();
+			}
+
+			if let Some(hours) = policy.kick_after_hours && let Some(joined_at) = member.joined_at && member_id != guild_owner_id {
+				let kick_at = joined_at.as_secs() + hours as i64 * SECONDS_PER_HOUR;
+				if Utc::now().timestamp() <= kick_at {
+					// synced again once the grace period is over, rather than waiting for the next resync.
+					if !is_dry_run {
+						schedule_unlinked_kick(guild_id, member_id, kick_at);
+					}
+				} else if let Some(reason) = role_hierarchy.check_member(guild_id, member_id, &roles, guild_owner_id, Permissions::KICK_MEMBERS) {
+					issues.push(SyncingIssue::UnmoderatableMember(reason));
+				} else {
+					member_status = MemberStatus::Kicked;
+					if !is_dry_run {
+						let reasoning = Reasoning {
+							reason: Some("Didn't link a HAKUMI account in time".into()),
+							user_facing_details: policy.kick_details.clone()
+						};
+						if remove_member(guild_id, member_id, &member_status, &reasoning, "Didn't link a HAKUMI account in time").await? {
+							issues.push(SyncingIssue::UndeliveredRemovalMessage);
+						}
+					}
+				}
+			}
+		} else {
+			// these roles only mark members as unlinked, so they're taken away once a member links.
+			for role_id in &policy.role_ids {
+				if new_roles.contains(role_id) && !assigned_role_ids.contains(role_id) {
+					if let Some(reason) = role_hierarchy.check(guild_id, *role_id) {
+						flag_unmanageable_role(&mut unmanageable_roles, guild_id, *role_id, reason);
+						continue;
+					}
+
+					new_roles.retain(|x| x != role_id);
+					role_changes.push(RoleChange {
+						kind: RoleChangeKind::Removed,
+						target_id: *role_id,
+						display_name: get_role_name(guild_id, *role_id)
+					});
+				}
+			}
+		}
+	}
+
+	if !unmanageable_roles.is_empty() {
+		issues.push(SyncingIssue::UnmanageableRoles(unmanageable_roles));
+	}
+
+	let target_nickname = match nickname_override.or(default_nickname).as_deref().map(NicknameTemplate::parse) {
+		Some(Ok(template)) => template
+			.render(guild_id, user_id, member_id)
+			.await?,
+		Some(Err(error)) => {
+			issues.push(SyncingIssue::InvalidNicknameTemplate(error));
+			None
+		},
 		None => None
 	};
 
 	let nickname_change = if let Some(target) = &target_nickname {
-		let guild_owner_id = CACHE
-			.discord
-			.guild(guild_id)
-			.await?
-			.owner_id;
 		let member = CACHE
 			.discord
 			.member(guild_id, member_id)
@@ -429,51 +957,103 @@ pub async fn sync_member(guild_id: Id<GuildMarker>, user_id: Option<HakuId<HakuU
 		} else { None }
 	} else { None };
 
-	let profile_changed = !member_status.removed() && (!role_changes.is_empty() || nickname_change.is_some());
-	if profile_changed {
+	let profile_changed = !member_status.removed() && (!role_changes.is_empty() || nickname_change.is_some() || timeout.is_some());
+	if profile_changed && !is_dry_run {
 		let mut request = DISCORD_CLIENT.update_guild_member(guild_id, member_id);
 		if !role_changes.is_empty() {
+			record_own_role_changes(guild_id, member_id, role_changes.iter().map(|x| x.target_id));
 			request = request.roles(&new_roles);
 		}
 		if nickname_change.is_some() {
 			request = request.nick(target_nickname.as_deref());
 		}
-		request.await?;
+		if let Some((until, reason)) = &timeout {
+			request = request
+				.communication_disabled_until(Some(Timestamp::from_secs(until.timestamp())?))
+				.reason(reason);
+		}
+		let response = request.await;
+		if response.is_err() {
+			forget_own_role_changes(guild_id, member_id, role_changes.iter().map(|x| x.target_id));
+		}
+		response?;
 	}
 
-	// TODO: better.
-	let role_changes2 = role_changes.clone();
-	tokio::spawn(async move {
-		if let Some(document) = CACHE.mellow.event_document(guild_id, DocumentKind::MemberSynced).await.unwrap() {
-			if let Some(document) = document.clone_if_ready() {
+	if !is_dry_run && !member_status.removed() && !pending_documents.is_empty() {
+		// a document that syncs the member would otherwise end up executing itself, or the document that executed it, forever.
+		let document_chain = match &initiator {
+			SyncingInitiator::VisualScriptingDocument { document_id, document_chain } =>
+				[document_chain.as_slice(), &[*document_id]].concat(),
+			_ => vec![]
+		};
+		for (document_id, met) in pending_documents {
+			if document_chain.contains(&document_id) {
+				continue;
+			}
+
+			let document = CACHE
+				.hakumi
+				.visual_scripting_document(document_id)
+				.await?
+				.clone_if_ready();
+			if let Some(document) = document {
 				let variables = Variable::create_map([
-					("member", variable_from_member(guild_id, member_id).await.unwrap()),
+					("member", variable_from_member(guild_id, member_id).await?),
 					("guild_id", guild_id.to_string().into()),
-					("profile_changes", Variable::create_map([
-						("roles", Variable::create_map([
-							("added", role_changes2.iter().filter_map(|x| if matches!(x.kind, RoleChangeKind::Added) { Some(x.target_id) } else { None }).collect::<Vec<Id<RoleMarker>>>().into()),
-							("removed", role_changes2.iter().filter_map(|x| if matches!(x.kind, RoleChangeKind::Removed) { Some(x.target_id) } else { None }).collect::<Vec<Id<RoleMarker>>>().into())
-						], None))
-					], None))
+					("criteria_met", met.into())
 				], None);
-				process_document(document, variables)
-					.await
-					.send_logs(guild_id)
-					.await
-					.unwrap();
+				let tracker = process_document_in_chain(document, variables, document_chain.clone())
+					.await;
+				if !tracker.items().is_empty() {
+					document_results.push(DocumentResult {
+						document_name: tracker.document_name().to_string(),
+						items: tracker
+							.items()
+							.iter()
+							.map(|x| x.display(guild_id))
+							.collect()
+					});
+				}
 			}
 		}
-	});
+	}
+
+	// TODO: better.
+	if !is_dry_run {
+		let role_changes2 = role_changes.clone();
+		tokio::spawn(async move {
+			if let Some(document) = CACHE.mellow.event_document(guild_id, DocumentKind::MemberSynced).await.unwrap() {
+				if let Some(document) = document.clone_if_ready() {
+					let variables = Variable::create_map([
+						("member", variable_from_member(guild_id, member_id).await.unwrap()),
+						("guild_id", guild_id.to_string().into()),
+						("profile_changes", Variable::create_map([
+							("roles", Variable::create_map([
+								("added", role_changes2.iter().filter_map(|x| if matches!(x.kind, RoleChangeKind::Added) { Some(x.target_id) } else { None }).collect::<Vec<Id<RoleMarker>>>().into()),
+								("removed", role_changes2.iter().filter_map(|x| if matches!(x.kind, RoleChangeKind::Removed) { Some(x.target_id) } else { None }).collect::<Vec<Id<RoleMarker>>>().into())
+							], None))
+						], None))
+					], None);
+					process_document(document, variables)
+						.await
+						.send_logs(guild_id)
+						.await
+						.unwrap();
+				}
+			}
+		});
+	}
 
 	if let Some(user_id) = user_id {
 		let connections = user_server_connections(guild_id, user_id)
 			.await?;
 		if !actions
+			.actions()
 			.iter()
 			.all(|action| action
 				.criteria
-				.items
-				.iter()
+				.leaf_items()
+				.into_iter()
 				.all(|e| e
 					.relevant_connection()
 					.map_or(true, |x| connections.iter().any(|e| x == e.kind))
@@ -491,97 +1071,452 @@ pub async fn sync_member(guild_id: Id<GuildMarker>, user_id: Option<HakuId<HakuU
 		member_status,
 		profile_changed,
 		nickname_change,
+		timeout_until: timeout.map(|x| x.0),
 		relevant_connections: used_connections,
-		user_id: member_id
+		document_results,
+		user_id: member_id,
+		trace
 	})
 }
 
-// this needs to move away from recursion
-#[async_recursion]
+/// Evaluates the criteria of a single action, `action_results` must already contain the outcome
+/// of every action this one depends on, see [`SyncActionGraph::evaluation_order`].
+/// Discord criteria are checked against `member` as it was before syncing began.
+#[allow(clippy::too_many_arguments)]
 pub async fn member_meets_action_criteria(
 	guild_id: Id<GuildMarker>,
 	user_id: Option<HakuId<HakuUserMarker>>,
+	member: &MemberModel,
 	action: &SyncActionModel,
-	all_actions: &Vec<SyncActionModel>,
+	action_results: &HashMap<HakuId<SyncActionMarker>, bool>,
 	connection_metadata: &ConnectionMetadata,
-	criteria_cache: &mut HashMap<(HakuId<SyncActionMarker>, usize), bool>,
+	criteria_cache: &mut HashMap<(HakuId<SyncActionMarker>, Vec<usize>), bool>,
 	used_connections: &mut Vec<HakuId<ConnectionMarker>>
 ) -> Result<bool> {
-	let criteria = &action.criteria;
+	let context = CriteriaContext {
+		guild_id,
+		user_id,
+		member,
+		action_id: action.id,
+		action_results,
+		connection_metadata,
+		now: Utc::now().timestamp()
+	};
+	criteria_met(&context, &action.criteria, &mut vec![], criteria_cache, used_connections)
+		.await
+}
+
+struct CriteriaContext<'a> {
+	guild_id: Id<GuildMarker>,
+	user_id: Option<HakuId<HakuUserMarker>>,
+	member: &'a MemberModel,
+	action_id: HakuId<SyncActionMarker>,
+	action_results: &'a HashMap<HakuId<SyncActionMarker>, bool>,
+	connection_metadata: &'a ConnectionMetadata,
+	now: i64
+}
+
+// items are cached by their path within the action's criteria, so nested items never share an entry.
+async fn criteria_met(
+	context: &CriteriaContext<'_>,
+	criteria: &Criteria,
+	path: &mut Vec<usize>,
+	criteria_cache: &mut HashMap<(HakuId<SyncActionMarker>, Vec<usize>), bool>,
+	used_connections: &mut Vec<HakuId<ConnectionMarker>>
+) -> Result<bool> {
+	// connections only count as relevant when the criteria they were used for are met as a whole.
+	let mut met_connections: Vec<HakuId<ConnectionMarker>> = vec![];
 	let mut total_met = 0;
 	let minimum_amount = criteria.quantifier.minimum();
 	for (key, item) in criteria.items.iter().enumerate() {
-		let cache_key = (action.id, key);
-		if criteria_cache.get(&cache_key).is_some_and(|x| *x) || match item {
-			CriteriaItem::HakumiUserConnection { connection_kind } => matches!(user_id, Some(user_id) if
-				user_server_connections(guild_id, user_id)
-					.await?
-					.into_iter()
-					.any(|x| &x.kind == connection_kind)
-			),
-			CriteriaItem::PatreonCampaignTierSubscription { tier_id, campaign_id } => if let Some(user_id) = user_id {
-				if let Some(pledge) = connection_metadata.patreon_pledges.iter().find(|x| x.user_id == user_id.value && x.campaign_id == *campaign_id && x.tiers.contains(tier_id)) {
-					if !used_connections.contains(&pledge.connection_id) {
-						used_connections.push(pledge.connection_id);
-					}
-					true
-				} else { false }
-			} else { false },
-			CriteriaItem::RobloxGroupMembership { .. } |
-			CriteriaItem::RobloxGroupMembershipRole { .. } |
-			CriteriaItem::RobloxGroupMembershipRoleRankInRange { .. } => if let Some(user_id) = user_id {
-				let connections = user_server_connections(guild_id, user_id)
-					.await?;
-				if let Some(connection) = connections
-					.into_iter()
-					.find(|x| x.is_roblox())
-				{
-					if !used_connections.contains(&connection.id) {
-						used_connections.push(connection.id);
-					}
+		path.push(key);
+		let cache_key = (context.action_id, path.clone());
+		let is_met = criteria_cache.get(&cache_key).is_some_and(|x| *x) ||
+			item_met(context, item, path, criteria_cache, &mut met_connections).await?;
+		path.pop();
 
-					let roblox_id = &connection.sub;
-					match item {
-						CriteriaItem::RobloxGroupMembership { group_id } =>
-							connection_metadata.roblox_memberships.iter()
-								.any(|e| &e.user_id == roblox_id && &e.group_id == group_id),
-						CriteriaItem::RobloxGroupMembershipRole { role_id, .. } =>
-							connection_metadata.roblox_memberships.iter()
-								.any(|e| &e.user_id == roblox_id && &e.role == role_id),
-						CriteriaItem::RobloxGroupMembershipRoleRankInRange { group_id, range_lower, range_upper } => {
-							connection_metadata.roblox_memberships.iter()
-								.any(|e| &e.user_id == roblox_id && &e.group_id == group_id && &e.rank >= range_lower && &e.rank <= range_upper)
-						},
-						_ => false
-					}
-				} else { false }
-			} else { false },
-			CriteriaItem::MellowServerSyncingActions { action_ids, quantifier } => {
-				let mut total_met = 0;
-				let minimum_amount = quantifier.minimum();
-				for action_id in action_ids {
-					if let Some(other_action) = all_actions.iter().find(|x| &x.id == action_id) {
-						if member_meets_action_criteria(guild_id, user_id, other_action, all_actions, connection_metadata, criteria_cache, used_connections).await? {
-							total_met += 1;
-							if minimum_amount == Some(total_met) {
-								break;
-							}
-						}
-					}
+		criteria_cache.insert(cache_key, is_met);
+		if is_met {
+			total_met += 1;
+			if minimum_amount == Some(total_met) {
+				break;
+			}
+		}
+	}
+
+	let is_met = criteria.quantifier.is_met(total_met, criteria.items.len());
+	if is_met {
+		for connection_id in met_connections {
+			use_connection(used_connections, connection_id);
+		}
+	}
+	Ok(is_met)
+}
+
+#[async_recursion]
+async fn item_met<'a>(
+	context: &CriteriaContext<'a>,
+	item: &CriteriaItem,
+	path: &mut Vec<usize>,
+	criteria_cache: &mut HashMap<(HakuId<SyncActionMarker>, Vec<usize>), bool>,
+	used_connections: &mut Vec<HakuId<ConnectionMarker>>
+) -> Result<bool> {
+	let CriteriaContext { guild_id, user_id, member, action_results, connection_metadata, now, .. } = *context;
+	Ok(match item {
+		CriteriaItem::Group(criteria) =>
+			criteria_met(context, criteria, path, criteria_cache, used_connections).await?,
+		// a negated item is met through the accounts that fail it, so none of them count as relevant.
+		CriteriaItem::Not { item } =>
+			!item_met(context, item, path, criteria_cache, &mut vec![]).await?,
+		CriteriaItem::DiscordMemberHasRole { role_id } => member.roles.contains(role_id),
+		CriteriaItem::DiscordMemberLacksRole { role_id } => !member.roles.contains(role_id),
+		CriteriaItem::DiscordUserAccountAge { days } =>
+			now - member.user_id.timestamp() / 1000 > *days as i64 * SECONDS_PER_DAY,
+		CriteriaItem::DiscordMemberMembershipAge { days } => member
+			.joined_at
+			.is_some_and(|x| now - x.as_secs() > *days as i64 * SECONDS_PER_DAY),
+		CriteriaItem::DiscordMemberBoosting => member.premium_since.is_some(),
+		CriteriaItem::DiscordMemberTimedOut => member
+			.communication_disabled_until
+			.is_some_and(|x| x.as_secs() > now),
+		// members may share several accounts of the same kind, the item is met if any of them satisfies it.
+		// only the accounts that did are counted as relevant connections.
+		CriteriaItem::GitHubOrganizationMembership { .. } |
+		CriteriaItem::GitHubRepositoryContributor { .. } |
+		CriteriaItem::GitHubSponsorship { .. } => if let Some(user_id) = user_id {
+			let connections = user_server_connections(guild_id, user_id)
+				.await?;
+			let mut is_met = false;
+			for connection in connections.into_iter().filter(|x| x.kind == ConnectionKind::GitHub) {
+				if connection_metadata.github_relations.iter().any(|e| e.user_id == connection.sub && github_relation_met(item, &e.kind)) {
+					use_connection(used_connections, connection.id);
+					is_met = true;
+				}
+			}
+			is_met
+		} else { false },
+		CriteriaItem::HakumiUserConnection { connection_kind } => matches!(user_id, Some(user_id) if
+			user_server_connections(guild_id, user_id)
+				.await?
+				.into_iter()
+				.any(|x| &x.kind == connection_kind)
+		),
+		CriteriaItem::PatreonCampaignTierSubscription { campaign_id, .. } |
+		CriteriaItem::PatreonCampaignPatronStatus { campaign_id, .. } |
+		CriteriaItem::PatreonCampaignPledgeAmount { campaign_id, .. } |
+		CriteriaItem::PatreonCampaignLifetimeSupport { campaign_id, .. } |
+		CriteriaItem::PatreonCampaignPledgeDuration { campaign_id, .. } => if let Some(user_id) = user_id {
+			let mut is_met = false;
+			for pledge in connection_metadata.patreon_pledges.iter().filter(|x| x.user_id == user_id.value && &x.campaign_id == campaign_id) {
+				if match item {
+					// former and declined patrons can keep their tiers listed, they're only subscribed while actively pledging.
+					CriteriaItem::PatreonCampaignTierSubscription { tier_id, .. } =>
+						pledge.attributes.patron_status == Some(PatronStatus::ActivePatron) && pledge.tiers.contains(tier_id),
+					CriteriaItem::PatreonCampaignPatronStatus { patron_status, .. } =>
+						pledge.attributes.patron_status.as_ref() == Some(patron_status),
+					CriteriaItem::PatreonCampaignPledgeAmount { minimum_cents, .. } =>
+						pledge.attributes.currently_entitled_amount_cents >= *minimum_cents,
+					CriteriaItem::PatreonCampaignLifetimeSupport { minimum_cents, .. } =>
+						pledge.attributes.campaign_lifetime_support_cents >= *minimum_cents,
+					CriteriaItem::PatreonCampaignPledgeDuration { days, .. } => pledge
+						.attributes
+						.pledge_relationship_start
+						.is_some_and(|x| now - x.timestamp() > *days as i64 * SECONDS_PER_DAY),
+					_ => false
+				} {
+					use_connection(used_connections, pledge.connection_id);
+					is_met = true;
+				}
+			}
+			is_met
+		} else { false },
+		CriteriaItem::RobloxGroupMembership { .. } |
+		CriteriaItem::RobloxGroupMembershipRole { .. } |
+		CriteriaItem::RobloxGroupMembershipRoleRankInRange { .. } => if let Some(user_id) = user_id {
+			let connections = user_server_connections(guild_id, user_id)
+				.await?;
+			let mut is_met = false;
+			for connection in connections.into_iter().filter(|x| x.is_roblox()) {
+				let roblox_id = &connection.sub;
+				if match item {
+					CriteriaItem::RobloxGroupMembership { group_id } =>
+						connection_metadata.roblox_memberships.iter()
+							.any(|e| &e.user_id == roblox_id && &e.group_id == group_id),
+					CriteriaItem::RobloxGroupMembershipRole { role_id, .. } =>
+						connection_metadata.roblox_memberships.iter()
+							.any(|e| &e.user_id == roblox_id && &e.role == role_id),
+					CriteriaItem::RobloxGroupMembershipRoleRankInRange { group_id, range_lower, range_upper } => {
+						connection_metadata.roblox_memberships.iter()
+							.any(|e| &e.user_id == roblox_id && &e.group_id == group_id && &e.rank >= range_lower && &e.rank <= range_upper)
+					},
+					_ => false
+				} {
+					use_connection(used_connections, connection.id);
+					is_met = true;
 				}
+			}
+			is_met
+		} else { false },
+		CriteriaItem::MellowServerSyncingActions { action_ids, quantifier } => {
+			let total_met = action_ids
+				.iter()
+				.filter(|x| action_results.get(x).is_some_and(|x| *x))
+				.count();
+			quantifier.is_met(total_met, action_ids.len())
+		}
+	})
+}
+
+fn use_connection(used_connections: &mut Vec<HakuId<ConnectionMarker>>, connection_id: HakuId<ConnectionMarker>) {
+	if !used_connections.contains(&connection_id) {
+		used_connections.push(connection_id);
+	}
+}
 
-				minimum_amount == Some(total_met) || total_met == action_ids.len()
+fn github_relation_met(item: &CriteriaItem, relation_kind: &GitHubRelationKind) -> bool {
+	match (item, relation_kind) {
+		(CriteriaItem::GitHubOrganizationMembership { organization }, GitHubRelationKind::OrganizationMember(name)) =>
+			organization.eq_ignore_ascii_case(name),
+		(CriteriaItem::GitHubRepositoryContributor { owner, repository }, GitHubRelationKind::RepositoryContributor(owner2, repository2)) =>
+			owner.eq_ignore_ascii_case(owner2) && repository.eq_ignore_ascii_case(repository2),
+		(CriteriaItem::GitHubSponsorship { account }, GitHubRelationKind::Sponsor(name)) =>
+			account.eq_ignore_ascii_case(name),
+		_ => false
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use mellow_models::{
+		discord::guild::MemberModel,
+		hakumi::user::connection::ConnectionKind,
+		mellow::server::sync_action::{ Criteria, CriteriaItem, Quantifier },
+		patreon::user_identity::MemberAttributes
+	};
+	use mellow_util::hakuid::{
+		marker::{ ConnectionMarker, UserMarker as HakuUserMarker },
+		HakuId
+	};
+	use once_cell::sync::Lazy;
+	use std::collections::HashMap;
+	use twilight_model::{
+		guild::MemberFlags,
+		id::Id
+	};
+	use uuid::Uuid;
+
+	use super::{ ConnectionMetadata, CriteriaContext, GitHubRelationKind, MemberStatus, PatreonPledge, SyncMemberResult, SyncingInitiator, SyncingIssue, UnmoderatableReason, criteria_met, get_github_relations_or_flag };
+	use crate::{
+		github,
+		server::logging::ServerLog
+	};
+
+	fn role(id: u64) -> CriteriaItem {
+		CriteriaItem::DiscordMemberHasRole { role_id: Id::new(id) }
+	}
+
+	fn not(item: CriteriaItem) -> CriteriaItem {
+		CriteriaItem::Not { item: Box::new(item) }
+	}
+
+	fn group(quantifier: Quantifier, items: Vec<CriteriaItem>) -> CriteriaItem {
+		CriteriaItem::Group(Criteria { items, quantifier })
+	}
+
+	// only items decided by the member and the metadata passed in are used, anything else would need the cache.
+	async fn evaluate(user_id: Option<HakuId<HakuUserMarker>>, role_ids: &[u64], connection_metadata: &ConnectionMetadata, criteria: &Criteria) -> (bool, Vec<HakuId<ConnectionMarker>>) {
+		let member = MemberModel {
+			avatar: None,
+			communication_disabled_until: None,
+			deaf: None,
+			flags: MemberFlags::empty(),
+			joined_at: None,
+			mute: None,
+			nick: None,
+			pending: false,
+			premium_since: None,
+			roles: role_ids.iter().map(|x| Id::new(*x)).collect(),
+			user_id: Id::new(1)
+		};
+		let context = CriteriaContext {
+			guild_id: Id::new(1),
+			user_id,
+			member: &member,
+			action_id: HakuId::new(Uuid::nil()),
+			action_results: &HashMap::new(),
+			connection_metadata,
+			now: 0
+		};
+		let mut used_connections = vec![];
+		let is_met = criteria_met(&context, criteria, &mut vec![], &mut HashMap::new(), &mut used_connections)
+			.await
+			.unwrap();
+		(is_met, used_connections)
+	}
+
+	async fn is_met(role_ids: &[u64], quantifier: Quantifier, items: Vec<CriteriaItem>) -> bool {
+		evaluate(None, role_ids, &ConnectionMetadata::default(), &Criteria { items, quantifier })
+			.await
+			.0
+	}
+
+	fn pledge(user_id: HakuId<HakuUserMarker>, connection_id: u128, currently_entitled_amount_cents: u64) -> PatreonPledge {
+		PatreonPledge {
+			campaign_id: "campaign".into(),
+			connection_id: HakuId::new(Uuid::from_u128(connection_id)),
+			tiers: vec![],
+			attributes: MemberAttributes {
+				currently_entitled_amount_cents,
+				..Default::default()
 			},
-		} {
-			criteria_cache.insert(cache_key, true);
+			user_id: user_id.value
+		}
+	}
 
-			total_met += 1;
-			if minimum_amount == Some(total_met) {
-				return Ok(true);
-			}
-		} else {
-			criteria_cache.insert(cache_key, false);
+	fn pledge_amount(minimum_cents: u64) -> CriteriaItem {
+		CriteriaItem::PatreonCampaignPledgeAmount {
+			campaign_id: "campaign".into(),
+			minimum_cents
 		}
 	}
-	Ok(minimum_amount.is_none() && total_met == criteria.items.len())
-}
\ No newline at end of file
+
+	#[tokio::test]
+	async fn quantifiers_count_met_items() {
+		assert!(is_met(&[1, 2], Quantifier::All, vec![role(1), role(2)]).await);
+		assert!(!is_met(&[1], Quantifier::All, vec![role(1), role(2)]).await);
+		assert!(is_met(&[2], Quantifier::AtLeast { value: 1 }, vec![role(1), role(2)]).await);
+		assert!(!is_met(&[1, 2], Quantifier::AtMost { value: 1 }, vec![role(1), role(2)]).await);
+		assert!(is_met(&[2], Quantifier::Exactly { value: 1 }, vec![role(1), role(2)]).await);
+		assert!(!is_met(&[1, 2], Quantifier::Exactly { value: 1 }, vec![role(1), role(2)]).await);
+	}
+
+	#[tokio::test]
+	async fn nested_groups_use_their_own_quantifier() {
+		let items = || vec![
+			role(1),
+			group(Quantifier::AtLeast { value: 1 }, vec![role(2), role(3)])
+		];
+		assert!(is_met(&[1, 3], Quantifier::All, items()).await);
+		assert!(!is_met(&[1], Quantifier::All, items()).await);
+		assert!(!is_met(&[3], Quantifier::All, items()).await);
+
+		let items = || vec![
+			group(Quantifier::All, vec![
+				role(1),
+				group(Quantifier::AtMost { value: 0 }, vec![role(2)])
+			])
+		];
+		assert!(is_met(&[1], Quantifier::All, items()).await);
+		assert!(!is_met(&[1, 2], Quantifier::All, items()).await);
+	}
+
+	#[tokio::test]
+	async fn negation_inverts_items_and_groups() {
+		assert!(is_met(&[1], Quantifier::All, vec![not(role(2))]).await);
+		assert!(!is_met(&[2], Quantifier::All, vec![not(role(2))]).await);
+
+		let items = || vec![not(group(Quantifier::All, vec![role(1), role(2)]))];
+		assert!(is_met(&[1], Quantifier::All, items()).await);
+		assert!(!is_met(&[1, 2], Quantifier::All, items()).await);
+
+		// negating twice changes nothing.
+		assert!(is_met(&[2], Quantifier::All, vec![not(not(role(2)))]).await);
+	}
+	#[tokio::test]
+	async fn any_shared_account_can_meet_an_item() {
+		let user_id = HakuId::new(Uuid::from_u128(1));
+		let metadata = ConnectionMetadata {
+			patreon_pledges: vec![pledge(user_id, 10, 100), pledge(user_id, 11, 500)],
+			..Default::default()
+		};
+		let criteria = Criteria { items: vec![pledge_amount(300)], quantifier: Quantifier::All };
+		let (is_met, used_connections) = evaluate(Some(user_id), &[], &metadata, &criteria).await;
+		assert!(is_met);
+		// only the account that met the item is relevant.
+		assert_eq!(used_connections, [HakuId::new(Uuid::from_u128(11))]);
+
+		let criteria = Criteria { items: vec![pledge_amount(50)], quantifier: Quantifier::All };
+		let (is_met, used_connections) = evaluate(Some(user_id), &[], &metadata, &criteria).await;
+		assert!(is_met);
+		assert_eq!(used_connections, [HakuId::new(Uuid::from_u128(10)), HakuId::new(Uuid::from_u128(11))]);
+
+		// other users' pledges are never counted.
+		let (is_met, _) = evaluate(Some(HakuId::new(Uuid::from_u128(2))), &[], &metadata, &criteria).await;
+		assert!(!is_met);
+	}
+
+	#[tokio::test]
+	async fn accounts_only_count_when_the_criteria_are_met() {
+		let user_id = HakuId::new(Uuid::from_u128(1));
+		let metadata = ConnectionMetadata {
+			patreon_pledges: vec![pledge(user_id, 10, 500)],
+			..Default::default()
+		};
+		let criteria = Criteria { items: vec![pledge_amount(300), role(1)], quantifier: Quantifier::All };
+		let (is_met, used_connections) = evaluate(Some(user_id), &[], &metadata, &criteria).await;
+		assert!(!is_met);
+		assert!(used_connections.is_empty());
+
+		let criteria = Criteria { items: vec![not(pledge_amount(1000))], quantifier: Quantifier::All };
+		let (is_met, used_connections) = evaluate(Some(user_id), &[], &metadata, &criteria).await;
+		assert!(is_met);
+		assert!(used_connections.is_empty());
+	}
+
+	#[tokio::test]
+	async fn github_relations_are_matched_to_accounts() {
+		Lazy::force(&github::tests::STAND_IN);
+
+		let accounts = [
+			("1".to_string(), Some("member1".to_string())),
+			("2".into(), Some("outsider".into())),
+			("202".into(), None)
+		];
+		let mut issues = vec![];
+		let relations = get_github_relations_or_flag(vec![
+			GitHubRelationKind::RepositoryContributor("owner".into(), "repository".into()),
+			GitHubRelationKind::OrganizationMember("organization".into())
+		], &accounts, &mut issues).await;
+		assert!(issues.is_empty());
+
+		let mut relations: Vec<(&str, bool)> = relations
+			.iter()
+			.map(|x| (x.user_id.as_str(), matches!(x.kind, GitHubRelationKind::RepositoryContributor(..))))
+			.collect();
+		relations.sort();
+		assert_eq!(relations, [("1", false), ("1", true), ("2", true)]);
+	}
+
+	#[tokio::test]
+	async fn github_failing_flags_it_as_unavailable() {
+		Lazy::force(&github::tests::STAND_IN);
+
+		let accounts = [("1".to_string(), Some("broken".to_string()))];
+		let mut issues = vec![];
+		let relations = get_github_relations_or_flag(vec![GitHubRelationKind::Sponsor("account".into())], &accounts, &mut issues)
+			.await;
+		assert!(relations.is_empty());
+		assert!(matches!(issues.as_slice(), [SyncingIssue::ServiceUnavailable(ConnectionKind::GitHub)]));
+	}
+
+	#[test]
+	fn unmoderatable_members_are_logged() {
+		let result = SyncMemberResult {
+			initiator: SyncingInitiator::Automatic,
+			issues: vec![SyncingIssue::UnmoderatableMember(UnmoderatableReason::NotBelowHighestRole)],
+			role_changes: vec![],
+			member_status: MemberStatus::Ok,
+			profile_changed: false,
+			nickname_change: None,
+			timeout_until: None,
+			relevant_connections: vec![],
+			document_results: vec![],
+			user_id: Id::new(1),
+			trace: None
+		};
+		assert!(matches!(
+			result.create_log(),
+			Some(ServerLog::ServerProfileSync { unmoderatable_reasons, .. }) if matches!(unmoderatable_reasons.as_slice(), [UnmoderatableReason::NotBelowHighestRole])
+		));
+	}
+}
@@ -0,0 +1,312 @@
+use mellow_cache::CACHE;
+use mellow_models::{
+	hakumi::user::connection::ConnectionKind,
+	patreon::user_identity::UserIdentityField
+};
+use mellow_util::hakuid::{
+	marker::{ ConnectionMarker, UserMarker as HakuUserMarker },
+	HakuId
+};
+use serde::Serialize;
+use twilight_model::id::{
+	marker::{ GuildMarker, UserMarker },
+	Id
+};
+
+use crate::{
+	util::user_primary_connection,
+	Result
+};
+
+// https://discord.com/developers/docs/resources/guild#modify-guild-member
+pub const NICKNAME_LENGTH_LIMIT: usize = 32;
+
+/// Positions are counted in characters, not bytes.
+#[derive(Clone, Debug, PartialEq, Serialize, thiserror::Error)]
+#[serde(rename_all = "snake_case")]
+pub enum NicknameTemplateError {
+	#[error("placeholder at position {0} is never closed")]
+	UnclosedPlaceholder(usize),
+
+	#[error("unexpected closing brace at position {0}")]
+	UnexpectedClosingBrace(usize),
+
+	#[error("placeholder at position {0} is empty")]
+	EmptyPlaceholder(usize),
+
+	#[error("unknown placeholder \"{0}\"")]
+	UnknownPlaceholder(String)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum ConnectionField {
+	Id,
+	Username,
+	DisplayName
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Placeholder {
+	Connection(ConnectionKind, ConnectionField),
+	PatreonTier
+}
+
+impl Placeholder {
+	fn parse(name: &str) -> Option<Self> {
+		let (connection_name, field_name) = name.split_once('_')?;
+		let connection_kind = match connection_name {
+			"discord" => ConnectionKind::Discord,
+			"github" => ConnectionKind::GitHub,
+			"roblox" => ConnectionKind::Roblox,
+			"youtube" => ConnectionKind::YouTube,
+			"patreon" => ConnectionKind::Patreon,
+			_ => return None
+		};
+		Some(match field_name {
+			"id" => Self::Connection(connection_kind, ConnectionField::Id),
+			"username" => Self::Connection(connection_kind, ConnectionField::Username),
+			"display_name" => Self::Connection(connection_kind, ConnectionField::DisplayName),
+			"tier" if connection_kind == ConnectionKind::Patreon => Self::PatreonTier,
+			_ => return None
+		})
+	}
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+	Text(String),
+	// each alternative is tried in order, the first one with a value is used.
+	Placeholder(Vec<Placeholder>)
+}
+
+/// A parsed `ServerModel::default_nickname`, such as `[{patreon_tier}] {roblox_display_name|discord_username}`.
+/// Literal braces are written as `{{` and `}}`.
+#[derive(Clone, Debug)]
+pub struct NicknameTemplate {
+	segments: Vec<Segment>
+}
+
+impl NicknameTemplate {
+	pub fn parse(template: &str) -> core::result::Result<Self, NicknameTemplateError> {
+		let mut segments: Vec<Segment> = vec![];
+		let mut text = String::new();
+		let mut chars = template.chars().enumerate().peekable();
+		while let Some((position, char)) = chars.next() {
+			match char {
+				'{' => {
+					if chars.next_if(|x| x.1 == '{').is_some() {
+						text.push('{');
+						continue;
+					}
+
+					let mut name = String::new();
+					loop {
+						match chars.next() {
+							Some((_, '}')) => break,
+							Some((_, char)) => name.push(char),
+							None => return Err(NicknameTemplateError::UnclosedPlaceholder(position))
+						}
+					}
+
+					let mut alternatives: Vec<Placeholder> = vec![];
+					for alternative in name.split('|').map(str::trim) {
+						if alternative.is_empty() {
+							return Err(NicknameTemplateError::EmptyPlaceholder(position));
+						}
+						alternatives.push(
+							Placeholder::parse(alternative)
+								.ok_or_else(|| NicknameTemplateError::UnknownPlaceholder(alternative.to_string()))?
+						);
+					}
+
+					if !text.is_empty() {
+						segments.push(Segment::Text(std::mem::take(&mut text)));
+					}
+					segments.push(Segment::Placeholder(alternatives));
+				},
+				'}' => if chars.next_if(|x| x.1 == '}').is_some() {
+					text.push('}');
+				} else {
+					return Err(NicknameTemplateError::UnexpectedClosingBrace(position));
+				},
+				_ => text.push(char)
+			}
+		}
+
+		if !text.is_empty() {
+			segments.push(Segment::Text(text));
+		}
+
+		Ok(Self {
+			segments
+		})
+	}
+
+	/// Renders the template for the given member, truncated to Discord's nickname length limit.
+	/// Returns `None` if any placeholder has no value, in which case the member's nickname is left alone.
+	pub async fn render(&self, guild_id: Id<GuildMarker>, user_id: Option<HakuId<HakuUserMarker>>, member_id: Id<UserMarker>) -> Result<Option<String>> {
+		let mut nickname = String::new();
+		for segment in self.segments.iter() {
+			match segment {
+				Segment::Text(text) => nickname.push_str(text),
+				Segment::Placeholder(alternatives) => {
+					let mut value: Option<String> = None;
+					for placeholder in alternatives {
+						value = resolve_placeholder(placeholder, guild_id, user_id, member_id)
+							.await?
+							.filter(|x| !x.is_empty());
+						if value.is_some() {
+							break;
+						}
+					}
+
+					match value {
+						Some(value) => nickname.push_str(&value),
+						None => return Ok(None)
+					}
+				}
+			}
+		}
+
+		let nickname: String = nickname
+			.chars()
+			.take(NICKNAME_LENGTH_LIMIT)
+			.collect();
+		let nickname = nickname.trim();
+		Ok(if nickname.is_empty() {
+			None
+		} else { Some(nickname.to_string()) })
+	}
+}
+
+async fn resolve_placeholder(placeholder: &Placeholder, guild_id: Id<GuildMarker>, user_id: Option<HakuId<HakuUserMarker>>, member_id: Id<UserMarker>) -> Result<Option<String>> {
+	Ok(match placeholder {
+		// the member's own discord account is always available, even if it isn't shared with the server.
+		Placeholder::Connection(ConnectionKind::Discord, field) => {
+			let user = CACHE
+				.discord
+				.user(member_id)
+				.await?;
+			Some(match field {
+				ConnectionField::Id => member_id.to_string(),
+				ConnectionField::Username => user.name.clone(),
+				ConnectionField::DisplayName => user.display_name().to_string()
+			})
+		},
+		Placeholder::Connection(connection_kind, field) => if let Some(user_id) = user_id {
+			user_primary_connection(guild_id, user_id, connection_kind)
+				.await?
+				.and_then(|x| match field {
+					ConnectionField::Id => Some(x.sub.clone()),
+					ConnectionField::Username => x.username.clone(),
+					ConnectionField::DisplayName => x.display_name.clone()
+				})
+		} else { None },
+		Placeholder::PatreonTier => if let Some(user_id) = user_id {
+			let connection_id = user_primary_connection(guild_id, user_id, &ConnectionKind::Patreon)
+				.await?
+				.map(|x| x.id);
+			match connection_id {
+				Some(connection_id) => patreon_tier_title(guild_id, connection_id).await?,
+				None => None
+			}
+		} else { None }
+	})
+}
+
+// only the tier of the server's own campaign is used, the member may well be pledging to other creators too.
+async fn patreon_tier_title(guild_id: Id<GuildMarker>, connection_id: HakuId<ConnectionMarker>) -> Result<Option<String>> {
+	let Some(campaign_id) = CACHE
+		.patreon
+		.campaign(guild_id)
+		.await?
+		.map(|x| x.id.clone())
+	else {
+		return Ok(None);
+	};
+	let Some(user_identity) = CACHE
+		.patreon
+		.user_identity(connection_id)
+		.await?
+	else {
+		return Ok(None);
+	};
+	let Some(included) = &user_identity.included else {
+		return Ok(None);
+	};
+
+	let tier_ids: Vec<&String> = included
+		.iter()
+		.filter_map(|x| match x {
+			UserIdentityField::Member(member) if member.relationships.campaign.data.id == campaign_id => Some(member),
+			_ => None
+		})
+		.flat_map(|x| x.relationships.currently_entitled_tiers.data.0.iter().map(|x| &x.id))
+		.collect();
+	Ok(included
+		.iter()
+		.find_map(|x| match x {
+			UserIdentityField::Tier(tier) if tier_ids.contains(&&tier.id) => tier.attributes.title.clone(),
+			_ => None
+		})
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use mellow_models::hakumi::user::connection::ConnectionKind;
+
+	use super::{ ConnectionField, NicknameTemplate, NicknameTemplateError, Placeholder, Segment };
+
+	fn parse(template: &str) -> Result<Vec<Segment>, NicknameTemplateError> {
+		NicknameTemplate::parse(template).map(|x| x.segments)
+	}
+
+	#[test]
+	fn parses_text_and_placeholders() {
+		assert_eq!(parse("[{patreon_tier}] {roblox_display_name | discord_username}"), Ok(vec![
+			Segment::Text("[".into()),
+			Segment::Placeholder(vec![Placeholder::PatreonTier]),
+			Segment::Text("] ".into()),
+			Segment::Placeholder(vec![
+				Placeholder::Connection(ConnectionKind::Roblox, ConnectionField::DisplayName),
+				Placeholder::Connection(ConnectionKind::Discord, ConnectionField::Username)
+			])
+		]));
+	}
+
+	#[test]
+	fn parses_escaped_braces() {
+		assert_eq!(parse("{{{github_id}}}"), Ok(vec![
+			Segment::Text("{".into()),
+			Segment::Placeholder(vec![Placeholder::Connection(ConnectionKind::GitHub, ConnectionField::Id)]),
+			Segment::Text("}".into())
+		]));
+		assert_eq!(parse("{{}}"), Ok(vec![Segment::Text("{}".into())]));
+	}
+
+	#[test]
+	fn rejects_unknown_placeholders() {
+		assert_eq!(parse("{roblox_rank}"), Err(NicknameTemplateError::UnknownPlaceholder("roblox_rank".into())));
+		assert_eq!(parse("{github_tier}"), Err(NicknameTemplateError::UnknownPlaceholder("github_tier".into())));
+		assert_eq!(parse("{discord}"), Err(NicknameTemplateError::UnknownPlaceholder("discord".into())));
+	}
+
+	#[test]
+	fn rejects_empty_placeholders() {
+		assert_eq!(parse("a {}"), Err(NicknameTemplateError::EmptyPlaceholder(2)));
+		assert_eq!(parse("{discord_id|}"), Err(NicknameTemplateError::EmptyPlaceholder(0)));
+	}
+
+	#[test]
+	fn rejects_unterminated_braces() {
+		assert_eq!(parse("abc {discord_id"), Err(NicknameTemplateError::UnclosedPlaceholder(4)));
+		assert_eq!(parse("abc }"), Err(NicknameTemplateError::UnexpectedClosingBrace(4)));
+	}
+
+	#[test]
+	fn reports_character_positions() {
+		assert_eq!(parse("ñé {discord_id"), Err(NicknameTemplateError::UnclosedPlaceholder(3)));
+		assert_eq!(parse("🌸}"), Err(NicknameTemplateError::UnexpectedClosingBrace(1)));
+	}
+}
@@ -0,0 +1,186 @@
+use mellow_cache::CACHE;
+use mellow_models::mellow::server::{
+	sync_action::{ Criteria, CriteriaItem, Quantifier, SyncActionKind },
+	SyncActionModel
+};
+use mellow_util::{
+	hakuid::{
+		marker::SyncActionMarker,
+		HakuId
+	},
+	DISCORD_CLIENT
+};
+use serde::Serialize;
+use twilight_model::id::{
+	marker::{ GuildMarker, RoleMarker },
+	Id
+};
+
+use super::SyncActionGraph;
+use crate::{
+	roblox::get_group_roles,
+	Result
+};
+
+// keeps the listing within discord's message length limit.
+const MAX_DISPLAYED_BINDS: usize = 25;
+
+/// One rank of a Roblox group, and the Discord role it's bound to.
+#[derive(Debug, Serialize)]
+pub struct RankBind {
+	pub rank: u8,
+	pub group_role_id: u64,
+	pub group_role_name: String,
+	/// `None` until applied, when there's no Discord role with the same name yet.
+	pub role_id: Option<Id<RoleMarker>>,
+	/// Set when the server already has an action for this rank, those are left as-is.
+	pub action_id: Option<HakuId<SyncActionMarker>>
+}
+
+/// The sync actions needed to give every member the role of their rank within a Roblox group.
+/// Generated actions share a role group, so members only ever hold the role of their highest rank.
+#[derive(Debug, Serialize)]
+pub struct RankBindPlan {
+	pub group_id: u64,
+	pub role_group: String,
+	pub binds: Vec<RankBind>
+}
+
+impl RankBindPlan {
+	pub async fn generate(guild_id: Id<GuildMarker>, group_id: u64) -> Result<Self> {
+		let actions = SyncActionGraph::get(guild_id)
+			.await?;
+		let mut group_roles = get_group_roles(group_id)
+			.await?;
+		group_roles.sort_by_key(|x| std::cmp::Reverse(x.rank));
+
+		let mut binds = vec![];
+		for group_role in group_roles {
+			// rank 0 is the guest role, which everyone outside of the group has.
+			if group_role.rank == 0 {
+				continue;
+			}
+			let Ok(group_role_id) = group_role.id.parse::<u64>() else {
+				continue;
+			};
+			let existing_action = actions
+				.actions()
+				.iter()
+				.filter(|action| action
+					.criteria
+					.items
+					.iter()
+					.any(|item| matches!(item, CriteriaItem::RobloxGroupMembershipRole { group_id: x, role_id } if *x == group_id && *role_id == group_role_id))
+				)
+				.find_map(|action| match &action.kind {
+					SyncActionKind::AssignRoles { role_ids, .. } => Some((action.id, role_ids.first().copied())),
+					_ => None
+				});
+			let (action_id, role_id) = match existing_action {
+				Some((action_id, role_id)) => (Some(action_id), role_id),
+				None => (None, CACHE
+					.discord
+					.roles
+					.iter()
+					.find(|x| x.key().0 == guild_id && x.name.eq_ignore_ascii_case(&group_role.display_name))
+					.map(|x| x.id)
+				)
+			};
+			binds.push(RankBind {
+				rank: group_role.rank,
+				group_role_id,
+				group_role_name: group_role.display_name,
+				role_id,
+				action_id
+			});
+		}
+
+		Ok(Self {
+			group_id,
+			role_group: format!("roblox.group.{group_id}"),
+			binds
+		})
+	}
+
+	/// Creates the missing Discord roles and sync actions, returns how many actions were created.
+	pub async fn apply(&mut self, guild_id: Id<GuildMarker>) -> Result<usize> {
+		// generated actions go after every existing one, ordered from the highest rank down.
+		let actions = SyncActionGraph::get(guild_id)
+			.await?;
+		let base_priority = actions
+			.actions()
+			.iter()
+			.chain(actions.cycles().iter().flatten())
+			.map(|x| x.priority + 1)
+			.max()
+			.unwrap_or_default();
+		let mut total_created = 0;
+		for bind in self.binds.iter_mut().filter(|x| x.action_id.is_none()) {
+			let is_new_role = bind.role_id.is_none();
+			let role_id = match bind.role_id {
+				Some(x) => x,
+				None => DISCORD_CLIENT
+					.create_role(guild_id)
+					.name(&bind.group_role_name)
+					.await?
+					.model()
+					.await?
+					.id
+			};
+
+			let action = match SyncActionModel::create(
+				guild_id,
+				format!("{} (Roblox rank {})", bind.group_role_name, bind.rank),
+				SyncActionKind::AssignRoles {
+					role_ids: vec![role_id],
+					can_remove: true,
+					enforced: false
+				},
+				Criteria {
+					items: vec![CriteriaItem::RobloxGroupMembershipRole {
+						group_id: self.group_id,
+						role_id: bind.group_role_id
+					}],
+					quantifier: Quantifier::All
+				},
+				base_priority + (u8::MAX - bind.rank) as i32,
+				Some(self.role_group.clone())
+			).await {
+				Ok(x) => x,
+				Err(error) => {
+					// a role nothing assigns would only be left for someone to clean up by hand.
+					if is_new_role && let Err(error) = DISCORD_CLIENT.delete_role(guild_id, role_id).await {
+						tracing::warn!(%guild_id, %role_id, %error, "failed to delete role of unsaved rank bind");
+					}
+					return Err(error.into());
+				}
+			};
+			bind.role_id = Some(role_id);
+			bind.action_id = Some(action.id);
+			CACHE
+				.mellow
+				.insert_sync_action(guild_id, action);
+			total_created += 1;
+		}
+
+		Ok(total_created)
+	}
+
+	pub fn display(&self) -> String {
+		let mut lines: Vec<String> = self.binds
+			.iter()
+			.take(MAX_DISPLAYED_BINDS)
+			.map(|x| format!("* Rank {} **{}** → {}", x.rank, x.group_role_name, match (x.role_id, x.action_id) {
+				(Some(role_id), Some(_)) => format!("<@&{role_id}> (already bound)"),
+				(Some(role_id), None) => format!("<@&{role_id}>"),
+				(None, Some(_)) => "nothing (already bound)".into(),
+				(None, None) => "a new role".into()
+			}))
+			.collect();
+		if self.binds.len() > MAX_DISPLAYED_BINDS {
+			lines.push(format!("*...and {} more*", self.binds.len() - MAX_DISPLAYED_BINDS));
+		}
+
+		lines.join("\n")
+	}
+}
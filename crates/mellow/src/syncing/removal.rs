@@ -0,0 +1,112 @@
+use mellow_cache::CACHE;
+use mellow_models::mellow::server::sync_action::Reasoning;
+use mellow_util::DISCORD_CLIENT;
+use twilight_http::{
+	api_error::ApiError,
+	error::ErrorType,
+	request::AuditLogReason
+};
+use twilight_model::id::{
+	marker::{ GuildMarker, UserMarker },
+	Id
+};
+use twilight_util::builder::embed::EmbedBuilder;
+
+use super::MemberStatus;
+use crate::{ Error, Result };
+
+const DEFAULT_TITLE: &str = "You have been {action} from {server}";
+
+// discord's error code for users that can't be sent direct messages, which usually means they've closed them.
+const CANNOT_SEND_MESSAGES_TO_USER: u64 = 50007;
+
+/// Bans or kicks a member depending on `member_status`, sending them the server's removal message first.
+/// Whether Mellow is able to remove the member at all must be checked beforehand, so they aren't told about a removal that doesn't happen.
+/// Returns whether the message couldn't be delivered because of the member's direct message settings.
+pub async fn remove_member(guild_id: Id<GuildMarker>, member_id: Id<UserMarker>, member_status: &MemberStatus, reasoning: &Reasoning, audit_log_reason: &str) -> Result<bool> {
+	let is_undelivered = match send_removal_message(guild_id, member_id, member_status, reasoning).await {
+		Ok(()) => false,
+		Err(error) if is_direct_messages_closed(&error) => true,
+		// anything else isn't down to the member, but shouldn't keep them from being removed either.
+		Err(error) => {
+			tracing::warn!(%guild_id, %member_id, %error, "failed to send removal message");
+			false
+		}
+	};
+
+	match member_status {
+		MemberStatus::Banned => {
+			DISCORD_CLIENT
+				.create_ban(guild_id, member_id)
+				.reason(audit_log_reason)
+				.await?;
+		},
+		_ => {
+			DISCORD_CLIENT
+				.remove_guild_member(guild_id, member_id)
+				.reason(audit_log_reason)
+				.await?;
+		}
+	}
+
+	Ok(is_undelivered)
+}
+
+fn is_direct_messages_closed(error: &Error) -> bool {
+	match error {
+		Error::TwilightHttp(error) => matches!(
+			error.kind(),
+			ErrorType::Response { error: ApiError::General(error), .. } if error.code == CANNOT_SEND_MESSAGES_TO_USER
+		),
+		_ => false
+	}
+}
+
+/// Sends the server's removal message to a member that is about to be banned or kicked.
+/// This has to happen beforehand, as Discord doesn't allow direct messages to users that no longer share a server with Mellow.
+async fn send_removal_message(guild_id: Id<GuildMarker>, member_id: Id<UserMarker>, member_status: &MemberStatus, reasoning: &Reasoning) -> Result<()> {
+	let server_name = CACHE
+		.discord
+		.guild(guild_id)
+		.await?
+		.name
+		.clone();
+	let message = CACHE
+		.mellow
+		.server(guild_id)
+		.and_then(|x| x.removal_message.clone())
+		.unwrap_or_default();
+
+	let action = match member_status {
+		MemberStatus::Banned => "banned",
+		_ => "kicked"
+	};
+	let details = reasoning.user_facing_details
+		.as_deref()
+		.unwrap_or_default();
+	let replace_placeholders = |text: &str| text
+		.replace("{server}", &server_name)
+		.replace("{action}", action)
+		.replace("{details}", details);
+
+	let mut embed = EmbedBuilder::new()
+		.title(replace_placeholders(message.title.as_deref().unwrap_or(DEFAULT_TITLE)));
+	if let Some(description) = message.description.as_deref().or(reasoning.user_facing_details.as_deref()) {
+		embed = embed.description(replace_placeholders(description));
+	}
+	if let Some(colour) = message.colour {
+		embed = embed.color(colour);
+	}
+
+	let channel = DISCORD_CLIENT
+		.create_private_channel(member_id)
+		.await?
+		.model()
+		.await?;
+	DISCORD_CLIENT
+		.create_message(channel.id)
+		.embeds(&[embed.build()])
+		.await?;
+
+	Ok(())
+}
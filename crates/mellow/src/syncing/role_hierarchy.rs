@@ -0,0 +1,130 @@
+use mellow_cache::CACHE;
+use mellow_util::DISCORD_APP_ID;
+use serde::Serialize;
+use twilight_model::{
+	guild::Permissions,
+	id::{
+		marker::{ GuildMarker, RoleMarker, UserMarker },
+		Id
+	}
+};
+
+use crate::Result;
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnmanageableReason {
+	MissingPermission,
+	AboveHighestRole,
+	Managed
+}
+
+impl UnmanageableReason {
+	pub fn display(&self) -> &'static str {
+		match self {
+			Self::MissingPermission => "Mellow is missing the Manage Roles permission",
+			Self::AboveHighestRole => "this role is above Mellow's highest role",
+			Self::Managed => "this role is managed by an integration"
+		}
+	}
+}
+
+/// Why Mellow can't ban, kick or time out a member.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnmoderatableReason {
+	Owner,
+	MissingPermission,
+	NotBelowHighestRole
+}
+
+impl UnmoderatableReason {
+	pub fn display(&self) -> &'static str {
+		match self {
+			Self::Owner => "they own the server",
+			Self::MissingPermission => "Mellow is missing the permission to do so",
+			Self::NotBelowHighestRole => "their highest role isn't below Mellow's highest role"
+		}
+	}
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct UnmanageableRole {
+	pub role_id: Id<RoleMarker>,
+	pub display_name: String,
+	pub reason: UnmanageableReason
+}
+
+/// What Mellow itself is allowed to do with roles in a server, worked out from the cached roles of its own member.
+/// Used to skip roles Discord would reject, instead of failing the entire member update.
+#[derive(Debug)]
+pub struct RoleHierarchy {
+	permissions: Permissions,
+	highest_position: i64
+}
+
+impl RoleHierarchy {
+	pub async fn get(guild_id: Id<GuildMarker>) -> Result<Self> {
+		let bot_roles = CACHE
+			.discord
+			.member(guild_id, DISCORD_APP_ID.cast())
+			.await?
+			.roles
+			.clone();
+
+		// the @everyone role shares its id with the server.
+		let mut permissions = CACHE
+			.discord
+			.role(guild_id, guild_id.cast())
+			.map_or(Permissions::empty(), |x| x.permissions);
+		let mut highest_position = 0;
+		for role_id in bot_roles {
+			if let Some(role) = CACHE.discord.role(guild_id, role_id) {
+				permissions |= role.permissions;
+				highest_position = highest_position.max(role.position);
+			}
+		}
+
+		Ok(Self {
+			permissions,
+			highest_position
+		})
+	}
+
+	fn has_permission(&self, permission: Permissions) -> bool {
+		self.permissions.intersects(Permissions::ADMINISTRATOR | permission)
+	}
+
+	/// Returns why the given role can't be assigned or removed by Mellow, if it can't be.
+	/// Roles missing from the cache are assumed to be fine, Discord will have the final say on those.
+	pub fn check(&self, guild_id: Id<GuildMarker>, role_id: Id<RoleMarker>) -> Option<UnmanageableReason> {
+		if !self.has_permission(Permissions::MANAGE_ROLES) {
+			return Some(UnmanageableReason::MissingPermission);
+		}
+
+		let role = CACHE
+			.discord
+			.role(guild_id, role_id)?;
+		if role.managed {
+			Some(UnmanageableReason::Managed)
+		} else if role.position >= self.highest_position {
+			Some(UnmanageableReason::AboveHighestRole)
+		} else { None }
+	}
+
+	/// Returns why the given member can't be moderated by Mellow with `permission`, such as [`Permissions::BAN_MEMBERS`], if they can't be.
+	/// Like [`Self::check`], roles missing from the cache are assumed to be fine.
+	pub fn check_member(&self, guild_id: Id<GuildMarker>, member_id: Id<UserMarker>, member_roles: &[Id<RoleMarker>], owner_id: Id<UserMarker>, permission: Permissions) -> Option<UnmoderatableReason> {
+		if member_id == owner_id {
+			Some(UnmoderatableReason::Owner)
+		} else if !self.has_permission(permission) {
+			Some(UnmoderatableReason::MissingPermission)
+		} else if member_roles
+			.iter()
+			.filter_map(|x| CACHE.discord.role(guild_id, *x))
+			.any(|x| x.position >= self.highest_position)
+		{
+			Some(UnmoderatableReason::NotBelowHighestRole)
+		} else { None }
+	}
+}
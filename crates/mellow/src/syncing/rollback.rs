@@ -0,0 +1,178 @@
+use chrono::{ DateTime, Utc };
+use mellow_cache::CACHE;
+use mellow_models::mellow::server::SyncHistoryModel;
+use mellow_util::{
+	hakuid::{
+		marker::SyncJobMarker,
+		HakuId
+	},
+	DISCORD_CLIENT
+};
+use serde::{ Deserialize, Serialize };
+use twilight_http::request::AuditLogReason;
+use twilight_model::id::{
+	marker::{ GuildMarker, UserMarker },
+	Id
+};
+
+use super::{
+	enforcement::{ forget_own_role_changes, record_own_role_changes },
+	MemberStatus, NicknameChange, RoleChange, RoleChangeKind, RoleHierarchy, UnmanageableRole, flag_unmanageable_role, get_role_name
+};
+use crate::{
+	server::logging::ServerLog,
+	Result
+};
+
+// only the parts of a stored sync result that can be reverted.
+#[derive(Deserialize)]
+struct RevertibleChanges {
+	role_changes: Vec<RoleChange>,
+	nickname_change: Option<NicknameChange>,
+	#[serde(default)]
+	timeout_until: Option<DateTime<Utc>>,
+	member_status: MemberStatus
+}
+
+#[derive(Debug, Serialize)]
+pub struct RollbackResult {
+	pub member_id: Id<UserMarker>,
+	pub role_changes: Vec<RoleChange>,
+	pub nickname_change: Option<NicknameChange>,
+	pub unbanned: bool,
+	pub timeout_removed: bool,
+	pub unmanageable_roles: Vec<UnmanageableRole>
+}
+
+impl RollbackResult {
+	pub fn changed(&self) -> bool {
+		!self.role_changes.is_empty() || self.nickname_change.is_some() || self.unbanned || self.timeout_removed
+	}
+
+	pub fn create_log(&self, initiator_id: Option<Id<UserMarker>>) -> Option<ServerLog> {
+		if self.changed() || !self.unmanageable_roles.is_empty() {
+			Some(ServerLog::SyncRollback {
+				initiator_id,
+				user_id: self.member_id,
+				role_changes: self.role_changes.clone(),
+				nickname_change: self.nickname_change.clone(),
+				unbanned: self.unbanned,
+				timeout_removed: self.timeout_removed,
+				unmanageable_roles: self.unmanageable_roles.clone()
+			})
+		} else { None }
+	}
+}
+
+/// Reverts the changes of a single recorded sync.
+/// Anything that has since changed again is left alone, so a rollback never undoes a manual edit made after the sync.
+/// Kicks can't be reverted, the member has to rejoin on their own, while a timeout from the sync is removed.
+pub async fn rollback_sync(guild_id: Id<GuildMarker>, entry: &mut SyncHistoryModel) -> Result<RollbackResult> {
+	let changes: RevertibleChanges = serde_json::from_value(entry.result.clone())?;
+	let member_id = entry.member_id;
+	let mut result = RollbackResult {
+		member_id,
+		role_changes: vec![],
+		nickname_change: None,
+		unbanned: false,
+		timeout_removed: false,
+		unmanageable_roles: vec![]
+	};
+
+	match changes.member_status {
+		MemberStatus::Banned => {
+			DISCORD_CLIENT
+				.delete_ban(guild_id, member_id)
+				.reason("Rolled back a previous sync")
+				.await?;
+			result.unbanned = true;
+		},
+		MemberStatus::Kicked => (),
+		MemberStatus::Ok => {
+			let member = CACHE
+				.discord
+				.member(guild_id, member_id)
+				.await?
+				.clone();
+			let role_hierarchy = RoleHierarchy::get(guild_id)
+				.await?;
+
+			let mut new_roles = member.roles.clone();
+			for role_change in changes.role_changes {
+				let role_id = role_change.target_id;
+				let kind = match role_change.kind {
+					RoleChangeKind::Added if new_roles.contains(&role_id) => RoleChangeKind::Removed,
+					RoleChangeKind::Removed if !new_roles.contains(&role_id) => RoleChangeKind::Added,
+					_ => continue
+				};
+				if let Some(reason) = role_hierarchy.check(guild_id, role_id) {
+					flag_unmanageable_role(&mut result.unmanageable_roles, guild_id, role_id, reason);
+					continue;
+				}
+
+				match kind {
+					RoleChangeKind::Added => new_roles.push(role_id),
+					RoleChangeKind::Removed => new_roles.retain(|x| *x != role_id)
+				}
+				result.role_changes.push(RoleChange {
+					kind,
+					target_id: role_id,
+					display_name: get_role_name(guild_id, role_id)
+				});
+			}
+
+			if let Some(NicknameChange(old_nickname, new_nickname)) = changes.nickname_change && member.nick == new_nickname {
+				result.nickname_change = Some(NicknameChange(new_nickname, old_nickname));
+			}
+
+			// only the timeout given by the sync itself is removed, one that has since been replaced or ran out is left alone.
+			if let Some(timeout_until) = changes.timeout_until && let Some(disabled_until) = member.communication_disabled_until {
+				result.timeout_removed = disabled_until.as_secs() == timeout_until.timestamp() && timeout_until > Utc::now();
+			}
+
+			if !result.role_changes.is_empty() || result.nickname_change.is_some() || result.timeout_removed {
+				let mut request = DISCORD_CLIENT.update_guild_member(guild_id, member_id);
+				if !result.role_changes.is_empty() {
+					record_own_role_changes(guild_id, member_id, result.role_changes.iter().map(|x| x.target_id));
+					request = request.roles(&new_roles);
+				}
+				if let Some(nickname_change) = &result.nickname_change {
+					request = request.nick(nickname_change.1.as_deref());
+				}
+				if result.timeout_removed {
+					request = request.communication_disabled_until(None);
+				}
+				let response = request
+					.reason("Rolled back a previous sync")
+					.await;
+				if response.is_err() {
+					forget_own_role_changes(guild_id, member_id, result.role_changes.iter().map(|x| x.target_id));
+				}
+				response?;
+			}
+		}
+	}
+
+	entry.mark_rolled_back()
+		.await?;
+
+	Ok(result)
+}
+
+/// Reverts every sync from a forceful sync of the entire server, returns how many members couldn't be rolled back alongside the results.
+pub async fn rollback_sync_job(guild_id: Id<GuildMarker>, sync_job_id: HakuId<SyncJobMarker>) -> Result<(Vec<RollbackResult>, u32)> {
+	let mut results = vec![];
+	let mut total_failed = 0;
+	for mut entry in SyncHistoryModel::get_for_sync_job(guild_id, sync_job_id).await? {
+		match rollback_sync(guild_id, &mut entry).await {
+			Ok(result) => results.push(result),
+			// members that have left the server since end up here too.
+			Err(error) => {
+				tracing::warn!(%guild_id, member_id = %entry.member_id, %error, "failed to roll back member sync");
+				total_failed += 1;
+			}
+		}
+	}
+
+	Ok((results, total_failed))
+}
@@ -0,0 +1,570 @@
+use mellow_models::{
+	hakumi::user::connection::ConnectionKind,
+	mellow::server::sync_action::{ CriteriaItem, Quantifier },
+	patreon::user_identity::PatronStatus
+};
+use mellow_util::hakuid::{
+	marker::{ ConnectionMarker, SyncActionMarker, UserMarker as HakuUserMarker },
+	HakuId
+};
+use serde::Serialize;
+use std::collections::{ HashMap, HashSet };
+use twilight_model::id::{
+	marker::GuildMarker,
+	Id
+};
+
+use super::{ ConnectionMetadata, SyncActionGraph, SyncingIssue, github_relation_met };
+use crate::{
+	util::user_server_connections,
+	Result
+};
+
+// keeps the explanation within discord's message length limit, leaving room for a heading.
+const MAX_DISPLAY_LENGTH: usize = 1800;
+
+/// How every sync action of a server was evaluated for a member, used to explain the outcome of a sync.
+#[derive(Debug, Serialize)]
+pub struct SyncTrace {
+	pub actions: Vec<ActionTrace>,
+	/// The ban, kick or cancel action that stopped the sync, actions after it weren't applied.
+	pub halted_by: Option<HakuId<SyncActionMarker>>
+}
+
+#[derive(Debug, Serialize)]
+pub struct ActionTrace {
+	pub action_id: HakuId<SyncActionMarker>,
+	pub display_name: String,
+	pub outcome: ActionOutcome,
+	pub quantifier: Quantifier,
+	pub items: Vec<ItemTrace>
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", content = "data", rename_all = "snake_case")]
+pub enum ActionOutcome {
+	Met,
+	NotMet,
+	/// Depends on a service that couldn't be reached, so it was left as-is.
+	Undetermined,
+	/// Met, but the given action ranks above it within its role group.
+	Outranked(HakuId<SyncActionMarker>)
+}
+
+#[derive(Debug, Serialize)]
+pub struct ItemTrace {
+	pub description: String,
+	/// `None` when the item wasn't checked, as the outcome was already decided by the items before it.
+	pub met: Option<bool>,
+	/// The accounts, memberships and actions the item was checked against.
+	pub evidence: Vec<TraceEvidence>,
+	/// Why the item couldn't be met, only set when it wasn't.
+	pub missing: Option<MissingData>,
+	/// The items within a group or negation.
+	pub items: Vec<ItemTrace>
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", content = "data", rename_all = "snake_case")]
+pub enum TraceEvidence {
+	Action {
+		action_id: HakuId<SyncActionMarker>,
+		met: bool
+	},
+	Connection {
+		connection_id: HakuId<ConnectionMarker>
+	},
+	PatreonPledge {
+		connection_id: HakuId<ConnectionMarker>,
+		patron_status: Option<PatronStatus>,
+		amount_cents: u64,
+		tier_ids: Vec<String>
+	},
+	RobloxMembership {
+		connection_id: HakuId<ConnectionMarker>,
+		role_id: u64,
+		rank: u8
+	}
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", content = "data", rename_all = "snake_case")]
+pub enum MissingData {
+	HakumiAccount,
+	Connection(ConnectionKind),
+	OAuthAuthorisation(ConnectionKind),
+	ServiceUnavailable(ConnectionKind),
+	PatreonPledge,
+	RobloxGroupMembership
+}
+
+impl MissingData {
+	pub fn display(&self) -> String {
+		match self {
+			Self::HakumiAccount => "you haven't been set up with mellow yet".into(),
+			Self::Connection(connection_kind) => format!("no {connection_kind:?} account has been shared with this server"),
+			Self::OAuthAuthorisation(connection_kind) => format!("your {connection_kind:?} connection needs to be reconnected"),
+			Self::ServiceUnavailable(connection_kind) => format!("{connection_kind:?} couldn't be reached"),
+			Self::PatreonPledge => "no pledge to this campaign".into(),
+			Self::RobloxGroupMembership => "not a member of this group".into()
+		}
+	}
+}
+
+impl SyncTrace {
+	/// Builds the trace from the outcome of evaluating every action, see [`super::member_meets_action_criteria`].
+	#[allow(clippy::too_many_arguments)]
+	pub(super) async fn new(
+		guild_id: Id<GuildMarker>,
+		user_id: Option<HakuId<HakuUserMarker>>,
+		actions: &SyncActionGraph,
+		action_results: &HashMap<HakuId<SyncActionMarker>, bool>,
+		criteria_cache: &HashMap<(HakuId<SyncActionMarker>, Vec<usize>), bool>,
+		undetermined_actions: &HashSet<HakuId<SyncActionMarker>>,
+		role_group_winners: &HashMap<&str, HakuId<SyncActionMarker>>,
+		undetermined_role_groups: &HashSet<&str>,
+		connection_metadata: &ConnectionMetadata
+	) -> Result<Self> {
+		let connections = match user_id {
+			Some(user_id) => user_server_connections(guild_id, user_id)
+				.await?
+				.into_iter()
+				.map(|x| (x.id, x.kind.clone(), x.sub.clone()))
+				.collect(),
+			None => vec![]
+		};
+		let context = TraceContext {
+			user_id,
+			connections,
+			action_results,
+			criteria_cache,
+			connection_metadata
+		};
+
+		Ok(Self {
+			actions: actions
+				.actions()
+				.iter()
+				.map(|action| {
+					let met = action_results.get(&action.id).is_some_and(|x| *x);
+					let outcome = if undetermined_actions.contains(&action.id) || action.role_group.as_deref().is_some_and(|x| undetermined_role_groups.contains(x)) {
+						ActionOutcome::Undetermined
+					} else if met && let Some(winner_id) = action.role_group.as_deref().and_then(|x| role_group_winners.get(x)) && *winner_id != action.id {
+						ActionOutcome::Outranked(*winner_id)
+					} else if met {
+						ActionOutcome::Met
+					} else { ActionOutcome::NotMet };
+					ActionTrace {
+						action_id: action.id,
+						display_name: action.display_name.clone(),
+						outcome,
+						quantifier: action.criteria.quantifier.clone(),
+						items: context.trace_items(action.id, &action.criteria.items, &mut vec![])
+					}
+				})
+				.collect(),
+			halted_by: None
+		})
+	}
+
+	pub fn display(&self) -> String {
+		let mut lines: Vec<String> = vec![];
+		if let Some(action_id) = self.halted_by {
+			lines.push(format!("*Syncing stopped at **{}**, the actions after it weren't applied.*", self.action_name(action_id)));
+		}
+		for action in &self.actions {
+			lines.push(format!("**{}** — {}, needs {} of:", action.display_name, match action.outcome {
+				ActionOutcome::Met => "met".into(),
+				ActionOutcome::NotMet => "not met".into(),
+				ActionOutcome::Undetermined => "left as-is, as a service it depends on couldn't be reached".into(),
+				ActionOutcome::Outranked(action_id) => format!("met, but **{}** ranks above it in its role group", self.action_name(action_id))
+			}, describe_quantifier(&action.quantifier).to_lowercase()));
+			display_items(&mut lines, &action.items, 0);
+		}
+		if lines.is_empty() {
+			return "This server doesn't have any sync actions yet.".into();
+		}
+
+		let mut content = String::new();
+		for (index, line) in lines.iter().enumerate() {
+			if content.len() + line.len() > MAX_DISPLAY_LENGTH {
+				content.push_str(&format!("*...and {} more line(s)*", lines.len() - index));
+				break;
+			}
+			content.push_str(line);
+			content.push('\n');
+		}
+
+		content
+	}
+
+	fn action_name(&self, action_id: HakuId<SyncActionMarker>) -> &str {
+		self.actions
+			.iter()
+			.find(|x| x.action_id == action_id)
+			.map_or("an unknown action", |x| &x.display_name)
+	}
+}
+
+fn display_items(lines: &mut Vec<String>, items: &[ItemTrace], depth: usize) {
+	for item in items {
+		let mut details: Vec<String> = item.evidence
+			.iter()
+			.filter_map(|x| match x {
+				TraceEvidence::PatreonPledge { amount_cents, .. } => Some(format!("pledging {}", format_cents(*amount_cents))),
+				TraceEvidence::RobloxMembership { rank, .. } => Some(format!("rank {rank}")),
+				_ => None
+			})
+			.collect();
+		if let Some(missing) = &item.missing {
+			details.push(missing.display());
+		}
+
+		lines.push(format!("{}- {} {}{}",
+			"  ".repeat(depth),
+			match item.met {
+				Some(true) => "✅",
+				Some(false) => "❌",
+				None => "➖"
+			},
+			item.description,
+			if details.is_empty() { String::new() } else { format!(" — *{}*", details.join(", ")) }
+		));
+		display_items(lines, &item.items, depth + 1);
+	}
+}
+
+// everything needed to describe how the items of an action were evaluated, gathered once per member.
+struct TraceContext<'a> {
+	user_id: Option<HakuId<HakuUserMarker>>,
+	connections: Vec<(HakuId<ConnectionMarker>, ConnectionKind, String)>,
+	action_results: &'a HashMap<HakuId<SyncActionMarker>, bool>,
+	criteria_cache: &'a HashMap<(HakuId<SyncActionMarker>, Vec<usize>), bool>,
+	connection_metadata: &'a ConnectionMetadata
+}
+
+impl TraceContext<'_> {
+	// items are looked up by the same paths they were cached under while evaluating.
+	fn trace_items(&self, action_id: HakuId<SyncActionMarker>, items: &[CriteriaItem], path: &mut Vec<usize>) -> Vec<ItemTrace> {
+		let mut traces = Vec::with_capacity(items.len());
+		for (key, item) in items.iter().enumerate() {
+			path.push(key);
+			let met = self.criteria_cache
+				.get(&(action_id, path.clone()))
+				.copied();
+			traces.push(self.trace_item(action_id, item, met, path));
+			path.pop();
+		}
+
+		traces
+	}
+
+	fn trace_item(&self, action_id: HakuId<SyncActionMarker>, item: &CriteriaItem, met: Option<bool>, path: &mut Vec<usize>) -> ItemTrace {
+		let mut evidence: Vec<TraceEvidence> = vec![];
+		let mut missing: Option<MissingData> = None;
+		let mut items: Vec<ItemTrace> = vec![];
+		match item {
+			CriteriaItem::Group(criteria) =>
+				items = self.trace_items(action_id, &criteria.items, path),
+			// the wrapped item is evaluated in place of the negation, so it shares the same path.
+			CriteriaItem::Not { item } =>
+				items.push(self.trace_item(action_id, item, met.map(|x| !x), path)),
+			CriteriaItem::GitHubOrganizationMembership { .. } |
+			CriteriaItem::GitHubRepositoryContributor { .. } |
+			CriteriaItem::GitHubSponsorship { .. } => {
+				for (connection_id, _, sub) in self.connections_of(&ConnectionKind::GitHub) {
+					if self.connection_metadata.github_relations.iter().any(|x| &x.user_id == sub && github_relation_met(item, &x.kind)) {
+						evidence.push(TraceEvidence::Connection { connection_id: *connection_id });
+					}
+				}
+				missing = self.missing_connection(ConnectionKind::GitHub);
+			},
+			CriteriaItem::HakumiUserConnection { connection_kind } => {
+				evidence.extend(self
+					.connections_of(connection_kind)
+					.map(|x| TraceEvidence::Connection { connection_id: x.0 })
+				);
+				missing = self.missing_connection(connection_kind.clone());
+			},
+			CriteriaItem::MellowServerSyncingActions { action_ids, .. } =>
+				evidence.extend(action_ids
+					.iter()
+					.map(|x| TraceEvidence::Action {
+						action_id: *x,
+						met: self.action_results.get(x).is_some_and(|x| *x)
+					})
+				),
+			CriteriaItem::PatreonCampaignTierSubscription { campaign_id, .. } |
+			CriteriaItem::PatreonCampaignPatronStatus { campaign_id, .. } |
+			CriteriaItem::PatreonCampaignPledgeAmount { campaign_id, .. } |
+			CriteriaItem::PatreonCampaignLifetimeSupport { campaign_id, .. } |
+			CriteriaItem::PatreonCampaignPledgeDuration { campaign_id, .. } => {
+				if let Some(user_id) = self.user_id {
+					evidence.extend(self.connection_metadata.patreon_pledges
+						.iter()
+						.filter(|x| x.user_id == user_id.value && &x.campaign_id == campaign_id)
+						.map(|x| TraceEvidence::PatreonPledge {
+							connection_id: x.connection_id,
+							patron_status: x.attributes.patron_status.clone(),
+							amount_cents: x.attributes.currently_entitled_amount_cents,
+							tier_ids: x.tiers.clone()
+						})
+					);
+				}
+				missing = self
+					.missing_connection(ConnectionKind::Patreon)
+					.or_else(|| evidence.is_empty().then_some(MissingData::PatreonPledge));
+			},
+			CriteriaItem::RobloxGroupMembership { group_id } |
+			CriteriaItem::RobloxGroupMembershipRole { group_id, .. } |
+			CriteriaItem::RobloxGroupMembershipRoleRankInRange { group_id, .. } => {
+				for (connection_id, _, sub) in self.connections_of(&ConnectionKind::Roblox) {
+					evidence.extend(self.connection_metadata.roblox_memberships
+						.iter()
+						.filter(|x| &x.user_id == sub && x.group_id == *group_id)
+						.map(|x| TraceEvidence::RobloxMembership {
+							connection_id: *connection_id,
+							role_id: x.role,
+							rank: x.rank
+						})
+					);
+				}
+				missing = self
+					.missing_connection(ConnectionKind::Roblox)
+					.or_else(|| evidence.is_empty().then_some(MissingData::RobloxGroupMembership));
+			},
+			_ => ()
+		}
+
+		ItemTrace {
+			description: describe_item(item),
+			met,
+			evidence,
+			missing: missing.filter(|_| met == Some(false)),
+			items
+		}
+	}
+
+	fn connections_of<'b>(&'b self, connection_kind: &'b ConnectionKind) -> impl Iterator<Item = &'b (HakuId<ConnectionMarker>, ConnectionKind, String)> {
+		self.connections
+			.iter()
+			.filter(move |x| &x.1 == connection_kind)
+	}
+
+	// why any item depending on a connection of this kind couldn't have been met, regardless of the item itself.
+	fn missing_connection(&self, connection_kind: ConnectionKind) -> Option<MissingData> {
+		let Some(user_id) = self.user_id else {
+			return Some(MissingData::HakumiAccount);
+		};
+		// issues with a single user's accounts, such as a failed roblox lookup, are kept apart from the server's.
+		let issues: Vec<&SyncingIssue> = self.connection_metadata.issues
+			.iter()
+			.chain(self.connection_metadata.user_issues.get(&user_id).into_iter().flatten())
+			.collect();
+		if issues.iter().any(|x| matches!(x, SyncingIssue::ServiceUnavailable(x) if *x == connection_kind)) {
+			Some(MissingData::ServiceUnavailable(connection_kind))
+		} else if issues.iter().any(|x| matches!(x, SyncingIssue::MissingOAuthAuthorisation(x) if *x == connection_kind)) {
+			Some(MissingData::OAuthAuthorisation(connection_kind))
+		} else if self.connections_of(&connection_kind).next().is_none() {
+			Some(MissingData::Connection(connection_kind))
+		} else { None }
+	}
+}
+
+fn describe_quantifier(quantifier: &Quantifier) -> String {
+	match quantifier {
+		Quantifier::All => "All".into(),
+		Quantifier::AtLeast { value } => format!("At least {value}"),
+		Quantifier::AtMost { value } => format!("At most {value}"),
+		Quantifier::Exactly { value } => format!("Exactly {value}")
+	}
+}
+
+fn describe_item(item: &CriteriaItem) -> String {
+	match item {
+		CriteriaItem::DiscordMemberHasRole { role_id } => format!("Has the <@&{role_id}> role"),
+		CriteriaItem::DiscordMemberLacksRole { role_id } => format!("Doesn't have the <@&{role_id}> role"),
+		CriteriaItem::DiscordUserAccountAge { days } => format!("Discord account is older than {days} day(s)"),
+		CriteriaItem::DiscordMemberMembershipAge { days } => format!("Joined this server more than {days} day(s) ago"),
+		CriteriaItem::DiscordMemberBoosting => "Is boosting this server".into(),
+		CriteriaItem::DiscordMemberTimedOut => "Is timed out".into(),
+		CriteriaItem::GitHubOrganizationMembership { organization } => format!("Is a public member of the {organization} GitHub organisation"),
+		CriteriaItem::GitHubRepositoryContributor { owner, repository } => format!("Has contributed to {owner}/{repository} on GitHub"),
+		CriteriaItem::GitHubSponsorship { account } => format!("Sponsors {account} on GitHub"),
+		CriteriaItem::Group(criteria) => format!("{} of the following", describe_quantifier(&criteria.quantifier)),
+		CriteriaItem::Not { .. } => "Not the following".into(),
+		CriteriaItem::HakumiUserConnection { connection_kind } => format!("Has shared a {connection_kind:?} account with this server"),
+		CriteriaItem::MellowServerSyncingActions { action_ids, quantifier } =>
+			format!("{} of {} other action(s) are met", describe_quantifier(quantifier), action_ids.len()),
+		CriteriaItem::RobloxGroupMembership { group_id } => format!("Is a member of Roblox group {group_id}"),
+		CriteriaItem::RobloxGroupMembershipRole { group_id, role_id } => format!("Has role {role_id} in Roblox group {group_id}"),
+		CriteriaItem::RobloxGroupMembershipRoleRankInRange { group_id, range_lower, range_upper } =>
+			format!("Has a rank between {range_lower} and {range_upper} in Roblox group {group_id}"),
+		CriteriaItem::PatreonCampaignTierSubscription { campaign_id, tier_id } => format!("Is subscribed to tier {tier_id} of Patreon campaign {campaign_id}"),
+		CriteriaItem::PatreonCampaignPatronStatus { campaign_id, patron_status } => format!("Is {} of Patreon campaign {campaign_id}", match patron_status {
+			PatronStatus::ActivePatron => "an active patron",
+			PatronStatus::DeclinedPatron => "a declined patron",
+			PatronStatus::FormerPatron => "a former patron"
+		}),
+		CriteriaItem::PatreonCampaignPledgeAmount { campaign_id, minimum_cents } =>
+			format!("Pledges at least {} to Patreon campaign {campaign_id}", format_cents(*minimum_cents)),
+		CriteriaItem::PatreonCampaignLifetimeSupport { campaign_id, minimum_cents } =>
+			format!("Has given at least {} to Patreon campaign {campaign_id} in total", format_cents(*minimum_cents)),
+		CriteriaItem::PatreonCampaignPledgeDuration { campaign_id, days } =>
+			format!("Has been a patron of Patreon campaign {campaign_id} for more than {days} day(s)")
+	}
+}
+
+// amounts are in the campaign's own currency, so no symbol is shown.
+fn format_cents(cents: u64) -> String {
+	format!("{}.{:02}", cents / 100, cents % 100)
+}
+
+#[cfg(test)]
+mod tests {
+	use mellow_models::{
+		hakumi::user::connection::ConnectionKind,
+		mellow::server::sync_action::{ Criteria, CriteriaItem, Quantifier }
+	};
+	use mellow_util::hakuid::HakuId;
+	use std::collections::HashMap;
+	use twilight_model::id::Id;
+	use uuid::Uuid;
+
+	use super::{ ActionOutcome, ActionTrace, ConnectionMetadata, ItemTrace, MissingData, SyncTrace, TraceContext, format_cents };
+	use crate::syncing::SyncingIssue;
+
+	fn role(id: u64) -> CriteriaItem {
+		CriteriaItem::DiscordMemberHasRole { role_id: Id::new(id) }
+	}
+
+	fn trace(user_id: Option<u128>, connection_metadata: &ConnectionMetadata, criteria_cache: &[(&[usize], bool)], items: &[CriteriaItem]) -> Vec<ItemTrace> {
+		let action_id = HakuId::new(Uuid::nil());
+		let criteria_cache: HashMap<_, _> = criteria_cache
+			.iter()
+			.map(|(path, met)| ((action_id, path.to_vec()), *met))
+			.collect();
+		let context = TraceContext {
+			user_id: user_id.map(|x| HakuId::new(Uuid::from_u128(x))),
+			connections: vec![],
+			action_results: &HashMap::new(),
+			criteria_cache: &criteria_cache,
+			connection_metadata
+		};
+		context.trace_items(action_id, items, &mut vec![])
+	}
+
+	#[test]
+	fn items_are_found_by_their_path() {
+		let items = [
+			role(1),
+			CriteriaItem::Group(Criteria {
+				items: vec![role(2), role(3)],
+				quantifier: Quantifier::AtLeast { value: 1 }
+			}),
+			role(4)
+		];
+		let traces = trace(None, &ConnectionMetadata::default(), &[(&[0], true), (&[1], true), (&[1, 0], true)], &items);
+		assert_eq!(traces[0].met, Some(true));
+		assert_eq!(traces[1].met, Some(true));
+		assert_eq!(traces[1].items[0].met, Some(true));
+		// items after the group's quantifier was satisfied, or after the action was decided, were never checked.
+		assert_eq!(traces[1].items[1].met, None);
+		assert_eq!(traces[2].met, None);
+	}
+
+	#[test]
+	fn negated_items_are_inverted() {
+		let items = [CriteriaItem::Not { item: Box::new(role(1)) }];
+		let traces = trace(None, &ConnectionMetadata::default(), &[(&[0], false)], &items);
+		assert_eq!(traces[0].met, Some(false));
+		assert_eq!(traces[0].items[0].met, Some(true));
+		assert_eq!(traces[0].items[0].description, "Has the <@&1> role");
+	}
+
+	#[test]
+	fn missing_data_is_only_given_for_unmet_items() {
+		let items = [
+			CriteriaItem::HakumiUserConnection { connection_kind: ConnectionKind::Roblox },
+			CriteriaItem::HakumiUserConnection { connection_kind: ConnectionKind::Roblox }
+		];
+		let traces = trace(None, &ConnectionMetadata::default(), &[(&[0], false), (&[1], true)], &items);
+		assert!(matches!(traces[0].missing, Some(MissingData::HakumiAccount)));
+		assert!(traces[1].missing.is_none());
+
+		let metadata = ConnectionMetadata {
+			issues: vec![SyncingIssue::ServiceUnavailable(ConnectionKind::Roblox)],
+			..Default::default()
+		};
+		let traces = trace(Some(1), &metadata, &[(&[0], false)], &items);
+		assert!(matches!(traces[0].missing, Some(MissingData::ServiceUnavailable(ConnectionKind::Roblox))));
+
+		let traces = trace(Some(1), &ConnectionMetadata::default(), &[(&[0], false)], &items);
+		assert!(matches!(traces[0].missing, Some(MissingData::Connection(ConnectionKind::Roblox))));
+	}
+
+	#[test]
+	fn issues_are_only_given_for_their_own_user() {
+		let items = [
+			CriteriaItem::HakumiUserConnection { connection_kind: ConnectionKind::Roblox },
+			CriteriaItem::HakumiUserConnection { connection_kind: ConnectionKind::Patreon }
+		];
+		let metadata = ConnectionMetadata {
+			user_issues: HashMap::from([(HakuId::new(Uuid::from_u128(1)), vec![
+				SyncingIssue::ServiceUnavailable(ConnectionKind::Roblox),
+				SyncingIssue::MissingOAuthAuthorisation(ConnectionKind::Patreon)
+			])]),
+			..Default::default()
+		};
+		let traces = trace(Some(1), &metadata, &[(&[0], false), (&[1], false)], &items);
+		assert!(matches!(traces[0].missing, Some(MissingData::ServiceUnavailable(ConnectionKind::Roblox))));
+		assert!(matches!(traces[1].missing, Some(MissingData::OAuthAuthorisation(ConnectionKind::Patreon))));
+
+		let traces = trace(Some(2), &metadata, &[(&[0], false), (&[1], false)], &items);
+		assert!(matches!(traces[0].missing, Some(MissingData::Connection(ConnectionKind::Roblox))));
+		assert!(matches!(traces[1].missing, Some(MissingData::Connection(ConnectionKind::Patreon))));
+	}
+
+	#[test]
+	fn display_names_the_halting_action_and_is_truncated() {
+		let action_id = HakuId::new(Uuid::from_u128(1));
+		let sync_trace = SyncTrace {
+			actions: vec![ActionTrace {
+				action_id,
+				display_name: "Ban alts".into(),
+				outcome: ActionOutcome::Met,
+				quantifier: Quantifier::All,
+				items: vec![]
+			}],
+			halted_by: Some(action_id)
+		};
+		let content = sync_trace.display();
+		assert!(content.starts_with("*Syncing stopped at **Ban alts**"));
+		assert!(content.contains("**Ban alts** — met, needs all of:"));
+
+		let sync_trace = SyncTrace {
+			actions: (0..100)
+				.map(|x| ActionTrace {
+					action_id: HakuId::new(Uuid::from_u128(x)),
+					display_name: format!("action {x}"),
+					outcome: ActionOutcome::NotMet,
+					quantifier: Quantifier::All,
+					items: vec![]
+				})
+				.collect(),
+			halted_by: None
+		};
+		let content = sync_trace.display();
+		assert!(content.len() < 2000);
+		assert!(content.ends_with("more line(s)*"));
+
+		assert_eq!(SyncTrace { actions: vec![], halted_by: None }.display(), "This server doesn't have any sync actions yet.");
+	}
+
+	#[test]
+	fn cents_are_formatted_with_two_decimals() {
+		assert_eq!(format_cents(0), "0.00");
+		assert_eq!(format_cents(5), "0.05");
+		assert_eq!(format_cents(1250), "12.50");
+	}
+}
\ No newline at end of file
@@ -1,6 +1,12 @@
 use dashmap::mapref::multiple::RefMulti;
 use mellow_cache::CACHE;
-use mellow_models::hakumi::user::ConnectionModel;
+use mellow_models::{
+	hakumi::user::{
+		connection::ConnectionKind,
+		ConnectionModel
+	},
+	mellow::server::UserSettingsModel
+};
 use mellow_util::hakuid::{
 	marker::{ ConnectionMarker, UserMarker },
 	HakuId
@@ -23,4 +29,26 @@ pub async fn user_server_connections(guild_id: Id<GuildMarker>, user_id: HakuId<
 		.connections(&user_connections)
 		.await?
 	)
+}
+
+/// Returns the connection of the given kind that the user marked as primary for this server, see [`UserSettingsModel::primary_connection`].
+pub async fn user_primary_connection(guild_id: Id<GuildMarker>, user_id: HakuId<UserMarker>, connection_kind: &ConnectionKind) -> Result<Option<RefMulti<'static, HakuId<ConnectionMarker>, ConnectionModel>>> {
+	let user_settings: UserSettingsModel = CACHE
+		.mellow
+		.user_settings(guild_id, user_id)
+		.await?
+		.clone();
+	let connections: Vec<_> = user_server_connections(guild_id, user_id)
+		.await?
+		.into_iter()
+		.filter(|x| &x.kind == connection_kind)
+		.collect();
+	let connection_ids: Vec<HakuId<ConnectionMarker>> = connections
+		.iter()
+		.map(|x| x.id)
+		.collect();
+	Ok(user_settings
+		.primary_connection(&connection_ids)
+		.and_then(|connection_id| connections.into_iter().find(|x| x.id == connection_id))
+	)
 }
\ No newline at end of file
@@ -25,6 +25,14 @@ impl ActionTracker {
 		}
 	}
 
+	pub fn document_name(&self) -> &str {
+		&self.document_name
+	}
+
+	pub fn items(&self) -> &[ActionTrackerItem] {
+		&self.items
+	}
+
 	pub async fn send_logs(self, guild_id: Id<GuildMarker>) -> Result<()> {
 		if !self.items.is_empty() {
 			send_logs(guild_id, vec![ServerLog::VisualScriptingDocumentResult {
@@ -78,4 +86,27 @@ pub enum ActionTrackerItem {
 	CreatedMessage(Id<ChannelMarker>, Id<MessageMarker>),
 	DeletedMessage(String, String),
 	CreatedThread(Id<ChannelMarker>, Id<ChannelMarker>)
+}
+
+impl ActionTrackerItem {
+	pub fn display(&self, guild_id: Id<GuildMarker>) -> String {
+		match self {
+			ActionTrackerItem::Error(element_kind, source) =>
+				format!("* Encountered an error at **{}**\n```diff\n- {}```\n", element_kind.display_name(), source),
+			ActionTrackerItem::AssignedMemberRole(user_id, role_id) =>
+				format!("* Assigned <@&{role_id}> to <@{user_id}>"),
+			ActionTrackerItem::RemovedMemberRole(user_id, role_id) =>
+				format!("* Removed <@&{role_id}> from <@{user_id}>"),
+			ActionTrackerItem::BannedMember(user_id) =>
+				format!("* Banned <@{user_id}> from the server"),
+			ActionTrackerItem::KickedMember(user_id) =>
+				format!("* Kicked <@{user_id}> from the server"),
+			ActionTrackerItem::CreatedMessage(channel_id, message_id) =>
+				format!("* Sent a message in <#{channel_id}>: https://discord.com/channels/{guild_id}/{channel_id}/{message_id}"),
+			ActionTrackerItem::DeletedMessage(channel_id, user_id) =>
+				format!("* Deleted a message in <#{channel_id}> by <@{user_id}>"),
+			ActionTrackerItem::CreatedThread(channel_id, thread_id) =>
+				format!("* Started a new thread in <#{channel_id}>: <#{thread_id}>")
+		}
+	}
 }
\ No newline at end of file
@@ -1,7 +1,13 @@
 use futures::StreamExt;
 use mellow_cache::CACHE;
 use mellow_models::hakumi::visual_scripting::{ variable::VariableInterpretAs, DocumentModel, ElementKind, Variable, VariableKind };
-use mellow_util::{ DISCORD_CLIENT, DISCORD_INTERACTION_CLIENT };
+use mellow_util::{
+	hakuid::{
+		marker::DocumentMarker,
+		HakuId
+	},
+	DISCORD_CLIENT, DISCORD_INTERACTION_CLIENT
+};
 use twilight_http::request::{
 	channel::reaction::RequestReactionType,
 	AuditLogReason
@@ -13,7 +19,10 @@ use twilight_model::id::{
 
 use crate::{
 	server::logging::send_logs,
-	syncing::{ SyncingInitiator, sync_single_user },
+	syncing::{
+		enforcement::{ forget_own_role_changes, record_own_role_changes },
+		SyncingInitiator, sync_single_user
+	},
 	Error, Result
 };
 
@@ -24,6 +33,12 @@ mod stream;
 use stream::ElementStream;
 
 pub async fn process_document(document: DocumentModel, variables: Variable) -> ActionTracker {
+	process_document_in_chain(document, variables, vec![])
+		.await
+}
+
+/// Same as [`process_document`], `document_chain` being the documents whose syncs led to this one being executed.
+pub async fn process_document_in_chain(document: DocumentModel, variables: Variable, document_chain: Vec<HakuId<DocumentMarker>>) -> ActionTracker {
 	let mut stream = ElementStream::new(document.definition.clone(), variables);
 	let mut tracker = ActionTracker::new(document.name.clone());
 	while let Some((element, variables)) = stream.next().await {
@@ -54,20 +69,34 @@ pub async fn process_document(document: DocumentModel, variables: Variable) -> A
 				ElementKind::AssignRoleToMember(data) => {
 					if let Some(member) = data.reference.resolve(&*variables.read().await) {
 						let user_id = member.get("id").cast_id();
-						DISCORD_CLIENT
-							.add_guild_member_role(member.get("guild_id").cast_id(), user_id, Id::new(data.value.parse()?))
+						let guild_id = member.get("guild_id").cast_id();
+						let role_id = Id::new(data.value.parse()?);
+						record_own_role_changes(guild_id, user_id, [role_id]);
+						let response = DISCORD_CLIENT
+							.add_guild_member_role(guild_id, user_id, role_id)
 							.reason("Triggered by a visual scripting element")
-							.await?;
+							.await;
+						if response.is_err() {
+							forget_own_role_changes(guild_id, user_id, [role_id]);
+						}
+						response?;
 						tracker.assigned_member_role(user_id, &data.value);
 					}
 				},
 				ElementKind::RemoveRoleFromMember(data) => {
 					if let Some(member) = data.reference.resolve(&*variables.read().await) {
 						let user_id = member.get("id").cast_id();
-						DISCORD_CLIENT
-							.remove_guild_member_role(member.get("guild_id").cast_id(), user_id, Id::new(data.value.parse()?))
+						let guild_id = member.get("guild_id").cast_id();
+						let role_id = Id::new(data.value.parse()?);
+						record_own_role_changes(guild_id, user_id, [role_id]);
+						let response = DISCORD_CLIENT
+							.remove_guild_member_role(guild_id, user_id, role_id)
 							.reason("Triggered by a visual scripting element")
-							.await?;
+							.await;
+						if response.is_err() {
+							forget_own_role_changes(guild_id, user_id, [role_id]);
+						}
+						response?;
 						tracker.removed_member_role(user_id, &data.value);
 					}
 				},
@@ -76,7 +105,10 @@ pub async fn process_document(document: DocumentModel, variables: Variable) -> A
 						let user_id = member.get("id").cast_id();
 						let guild_id = member.get("guild_id").cast_id();
 						if let Some(haku_id) = CACHE.hakumi.user_by_discord(guild_id, user_id) .await? {
-							let result = sync_single_user(guild_id, haku_id, user_id, SyncingInitiator::VisualScriptingDocument(document.id), None)
+							let result = sync_single_user(guild_id, haku_id, user_id, SyncingInitiator::VisualScriptingDocument {
+								document_id: document.id,
+								document_chain: document_chain.clone()
+							}, None, false, false)
 								.await?;
 							if let Some(result_log) = result.create_log() {
 								send_logs(guild_id, vec![result_log])
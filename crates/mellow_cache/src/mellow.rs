@@ -125,6 +125,14 @@ impl MellowCache {
 		})
 	}
 
+	/// Adds a newly created sync action, servers whose actions haven't been loaded yet will pick it up from the database.
+	pub fn insert_sync_action(&self, guild_id: Id<GuildMarker>, model: SyncActionModel) {
+		if let Some(action_ids) = self.server_sync_actions.get(&guild_id) {
+			action_ids.insert(model.id);
+		}
+		self.sync_actions.insert(model.id, model);
+	}
+
 	pub async fn server_visual_scripting_documents(&self, guild_id: Id<GuildMarker>) -> Result<Vec<HakuId<DocumentMarker>>> {
 		Ok(match self.server_visual_scripting_documents.get(&guild_id) {
 			Some(model) => model
@@ -157,11 +165,13 @@ impl MellowCache {
 			}
 		}
 	
-		Ok(self.sync_actions
+		let mut models: Vec<_> = self.sync_actions
 			.iter()
 			.filter(|x| sync_action_ids.contains(&x.id))
-			.collect()
-		)
+			.collect();
+		models.sort_by_key(|x| (x.priority, x.id));
+
+		Ok(models)
 	}
 
 	pub async fn user_settings(&self, guild_id: Id<GuildMarker>, user_id: HakuId<HakuUserMarker>) -> Result<Ref<'_, (Id<GuildMarker>, HakuId<HakuUserMarker>), UserSettingsModel>> {
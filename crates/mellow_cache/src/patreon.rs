@@ -110,11 +110,12 @@ impl PatreonCache {
 				let campaign: GetCampaign = get_json("https://www.patreon.com/api/oauth2/v2/campaigns?include=tiers&fields%5Btier%5D=patron_count")
 					.header("authorization", auth_header)
 					.await?;
-				let Some(included) = campaign.included else {
+				let (Some(data), Some(included)) = (campaign.data.into_iter().next(), campaign.included) else {
 					return Err(Error::ModelNotFound);
 				};
 				Some(self.campaigns.entry(guild_id)
 					.insert(CampaignModel {
+						id: data.id,
 						tiers: included
 							.into_iter()
 							.map(|x| Tier {
@@ -191,7 +192,7 @@ impl PatreonCache {
 
 					let access_token = authorisation.access_token.clone();
 					let token_type = authorisation.token_type.clone();
-					let new_model: UserIdentityModel = get_json("https://www.patreon.com/api/oauth2/v2/identity?include=memberships.campaign,memberships.currently_entitled_tiers&fields%5Bmember%5D=patron_status")
+					let new_model: UserIdentityModel = get_json("https://www.patreon.com/api/oauth2/v2/identity?include=memberships.campaign,memberships.currently_entitled_tiers&fields%5Bmember%5D=patron_status,currently_entitled_amount_cents,campaign_lifetime_support_cents,pledge_relationship_start&fields%5Btier%5D=title")
 						.header("authorization", format!("{token_type} {access_token}"))
 						.await?;
 					Some(self.user_identities.entry(connection_id)
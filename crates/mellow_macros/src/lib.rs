@@ -10,6 +10,7 @@ struct CommandArgs {
 	slash: bool,
 	rename: Option<String>,
 	message: bool,
+	options: Option<syn::Path>,
 	description: Option<String>,
 	default_member_permissions: Option<String>
 }
@@ -29,6 +30,13 @@ fn wrap_option_to_string<T: quote::ToTokens>(literal: Option<T>) -> syn::Expr {
     }
 }
 
+fn wrap_option_path(path: Option<syn::Path>) -> syn::Expr {
+	match path {
+		Some(path) => syn::parse_quote! { Some(#path) },
+		None => syn::parse_quote! { None },
+	}
+}
+
 fn create_command(args: TokenStream, mut function: syn::ItemFn) -> Result<TokenStream, darling::Error> {
 	let args = darling::ast::NestedMeta::parse_meta_list(args.into())?;
     let args = <CommandArgs as darling::FromMeta>::from_list(&args)?;
@@ -55,6 +63,7 @@ fn create_command(args: TokenStream, mut function: syn::ItemFn) -> Result<TokenS
 	let is_user = args.user;
 	let is_slash = args.slash;
 	let is_message = args.message;
+	let options = wrap_option_path(args.options);
 	let description = wrap_option_to_string(args.description);
 	let default_member_permissions = wrap_option_to_string(args.default_member_permissions);
 	Ok(TokenStream::from(quote::quote! {
@@ -67,6 +76,7 @@ fn create_command(args: TokenStream, mut function: syn::ItemFn) -> Result<TokenS
 				is_user: #is_user,
 				is_slash: #is_slash,
 				is_message: #is_message,
+				options: #options,
 				description: #description,
 				default_member_permissions: #default_member_permissions
 			}
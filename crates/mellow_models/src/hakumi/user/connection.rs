@@ -10,7 +10,7 @@ use mellow_util::{
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
 use serde::Deserialize;
-use serde_repr::Deserialize_repr;
+use serde_repr::{ Deserialize_repr, Serialize_repr };
 use std::pin::Pin;
 use twilight_model::id::{
 	marker::UserMarker,
@@ -131,6 +131,12 @@ impl ConnectionModel {
 		}
 	}
 
+	/// Whether anything sync action criteria depend on differs between the two, being the account itself and whether it's still authorised.
+	pub fn affects_criteria(&self, other: &Self) -> bool {
+		self.sub != other.sub || self.kind != other.kind ||
+			self.oauth_authorisations.is_empty() != other.oauth_authorisations.is_empty()
+	}
+
 	pub fn is_discord(&self) -> bool {
 		matches!(self.kind, ConnectionKind::Discord)
 	}
@@ -144,7 +150,7 @@ impl ConnectionModel {
 	}
 }
 
-#[derive(Clone, Debug, Deserialize_repr, FromPrimitive, PartialEq)]
+#[derive(Clone, Debug, Deserialize_repr, FromPrimitive, PartialEq, Serialize_repr)]
 #[repr(u8)]
 pub enum ConnectionKind {
 	Discord,
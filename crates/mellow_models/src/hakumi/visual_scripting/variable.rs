@@ -71,6 +71,7 @@ impl Variable {
 		match &self.kind {
 			VariableKind::String(x) => x.clone(),
 			VariableKind::UnsignedInteger(x) => x.to_string(),
+			VariableKind::Boolean(x) => x.to_string(),
 			_ => panic!()
 		}
 	}
@@ -80,7 +81,8 @@ impl Variable {
 			VariableKind::Map(x) => x.is_empty(),
 			VariableKind::List(x) => x.is_empty(),
 			VariableKind::String(x) => x.is_empty(),
-			VariableKind::UnsignedInteger(_) => false
+			VariableKind::UnsignedInteger(_) |
+			VariableKind::Boolean(_) => false
 		}
 	}
 
@@ -89,7 +91,8 @@ impl Variable {
 			VariableKind::Map(x) => x.iter().any(|x| x.1 == variable),
 			VariableKind::List(x) => x.iter().any(|x| x == variable),
 			VariableKind::String(x) => x.contains(variable.cast_str()),
-			VariableKind::UnsignedInteger(_) => false
+			VariableKind::UnsignedInteger(_) |
+			VariableKind::Boolean(_) => false
 		}
 	}
 
@@ -148,6 +151,15 @@ impl From<u64> for Variable {
 	}
 }
 
+impl From<bool> for Variable {
+	fn from(value: bool) -> Self {
+		Variable {
+			kind: VariableKind::Boolean(value),
+			interpret_as: VariableInterpretAs::NonSpecific
+		}
+	}
+}
+
 impl<T> From<Id<T>> for Variable {
 	fn from(value: Id<T>) -> Self {
 		value.to_string().into()
@@ -213,7 +225,7 @@ impl From<&serde_json::Value> for Variable {
 		use serde_json::Value;
 		match value {
 			Value::Null => unimplemented!(),
-			Value::Bool(_) => unimplemented!(),
+			Value::Bool(x) => VariableKind::Boolean(*x),
 			Value::Array(x) => VariableKind::List(x.iter().map(|x| x.into()).collect()),
 			Value::Number(_) => unimplemented!(),
 			Value::Object(x) => VariableKind::Map(x.iter().map(|x| (x.0.clone(), x.1.into())).collect()),
@@ -228,7 +240,8 @@ pub enum VariableKind {
 	List(Vec<Variable>),
 
 	String(String),
-	UnsignedInteger(u64)
+	UnsignedInteger(u64),
+	Boolean(bool)
 }
 
 impl From<VariableKind> for Variable {
@@ -7,9 +7,12 @@ use mellow_util::{
 	PG_POOL
 };
 use serde::Deserialize;
-use std::pin::Pin;
+use std::{
+	pin::Pin,
+	time::Duration
+};
 use twilight_model::id::{
-	marker::{ ChannelMarker, GuildMarker },
+	marker::{ ChannelMarker, GuildMarker, RoleMarker },
 	Id
 };
 
@@ -24,16 +27,56 @@ pub use command::CommandModel;
 pub mod sync_action;
 pub use sync_action::SyncActionModel;
 
+pub mod sync_history;
+pub use sync_history::SyncHistoryModel;
+
+pub mod sync_job;
+pub use sync_job::SyncJobModel;
+
 pub mod user_settings;
 pub use user_settings::UserSettingsModel;
 
+// resyncing a server touches every member, so it shouldn't happen more often than this.
+const MIN_RESYNC_INTERVAL_MINUTES: u32 = 30;
+
 #[derive(Debug, Deserialize)]
 pub struct ServerModel {
 	pub id: Id<GuildMarker>,
 	pub logging_types: u8,
 	pub default_nickname: Option<String>,
 	pub logging_channel_id: Option<Id<ChannelMarker>>,
-	pub allow_forced_syncing: bool
+	pub allow_forced_syncing: bool,
+	/// Whether linked members are synced on their own when they join, and when a connection they've shared changes.
+	pub auto_sync_members: bool,
+	pub resync_interval_minutes: Option<u32>,
+	pub removal_message: Option<RemovalMessage>,
+	pub unlinked_member_policy: Option<UnlinkedMemberPolicy>
+}
+
+/// The embed sent to members before a sync action bans or kicks them.
+/// `{server}`, `{action}` and `{details}` are replaced with the server name, "banned" or "kicked", and the action's user-facing details.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct RemovalMessage {
+	pub title: Option<String>,
+	pub description: Option<String>,
+	pub colour: Option<u32>
+}
+
+/// How members without a HAKUMI account are treated whenever they're synced, on top of the server's sync actions.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct UnlinkedMemberPolicy {
+	/// Assigned while the member is unlinked, and removed again once they link.
+	#[serde(default)]
+	pub role_ids: Vec<Id<RoleMarker>>,
+	/// Removed while the member is unlinked.
+	#[serde(default)]
+	pub removed_role_ids: Vec<Id<RoleMarker>>,
+	/// A nickname template, only placeholders for the member's Discord account have a value.
+	pub nickname: Option<String>,
+	/// Members that are still unlinked this many hours after joining are kicked, once the grace period is over.
+	pub kick_after_hours: Option<u32>,
+	/// Shown to kicked members, see [`RemovalMessage`].
+	pub kick_details: Option<String>
 }
 
 impl ServerModel {
@@ -50,7 +93,7 @@ impl ServerModel {
 			.collect();
 		Ok(sqlx::query!(
 			"
-			SELECT id, logging_types, default_nickname, logging_channel_id, allow_forced_syncing
+			SELECT id, logging_types, default_nickname, logging_channel_id, allow_forced_syncing, auto_sync_members, resync_interval_minutes, removal_message, unlinked_member_policy
 			FROM mellow_servers
 			WHERE id = ANY($1)
 			",
@@ -63,7 +106,11 @@ impl ServerModel {
 					logging_types: record.logging_types as u8,
 					default_nickname: record.default_nickname,
 					logging_channel_id: record.logging_channel_id.map(|x| Id::new(x as u64)),
-					allow_forced_syncing: record.allow_forced_syncing
+					allow_forced_syncing: record.allow_forced_syncing,
+					auto_sync_members: record.auto_sync_members,
+					resync_interval_minutes: record.resync_interval_minutes.map(|x| x as u32),
+					removal_message: record.removal_message.and_then(|x| serde_json::from_value(x).ok()),
+					unlinked_member_policy: record.unlinked_member_policy.and_then(|x| serde_json::from_value(x).ok())
 				});
 
 				async move { Ok(acc) }
@@ -72,6 +119,13 @@ impl ServerModel {
 		)
 	}
 
+	/// How often every member of the server should be synced automatically, if at all.
+	pub fn resync_interval(&self) -> Option<Duration> {
+		self.resync_interval_minutes
+			.filter(|x| *x > 0)
+			.map(|x| Duration::from_secs(x.max(MIN_RESYNC_INTERVAL_MINUTES) as u64 * 60))
+	}
+
 	pub async fn oauth_authorisations(guild_id: Id<GuildMarker>) -> Result<Vec<OAuthAuthorisationModel>> {
 		Ok(sqlx::query!(
 			"
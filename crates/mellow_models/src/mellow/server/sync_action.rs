@@ -1,20 +1,21 @@
 use futures::TryStreamExt;
 use mellow_util::{
 	hakuid::{
-		marker::SyncActionMarker,
+		marker::{ DocumentMarker, SyncActionMarker },
 		HakuId
 	},
 	PG_POOL
 };
-use serde::Deserialize;
+use serde::{ Deserialize, Serialize };
 use std::pin::Pin;
 use twilight_model::id::{
-	marker::RoleMarker,
+	marker::{ GuildMarker, RoleMarker },
 	Id
 };
 
 use crate::{
 	hakumi::user::connection::ConnectionKind,
+	patreon::user_identity::PatronStatus,
 	Result
 };
 
@@ -23,7 +24,12 @@ pub struct SyncActionModel {
 	pub id: HakuId<SyncActionMarker>,
 	pub kind: SyncActionKind,
 	pub criteria: Criteria,
-	pub display_name: String
+	pub display_name: String,
+	/// Actions are evaluated and applied in ascending order of this value.
+	pub priority: i32,
+	/// Of all `AssignRoles` actions sharing a group, only the first one (by priority) that is met assigns its roles,
+	/// the roles of every other action in the group are removed.
+	pub role_group: Option<String>
 }
 
 impl SyncActionModel {
@@ -33,6 +39,41 @@ impl SyncActionModel {
 			.map(|x| x.into_iter().next())
 	}
 
+	pub async fn create(server_id: Id<GuildMarker>, display_name: String, kind: SyncActionKind, criteria: Criteria, priority: i32, role_group: Option<String>) -> Result<Self> {
+		// the kind and its data live in separate columns, so they're split back apart here.
+		let mut kind_value = serde_json::to_value(&kind)?;
+		let kind_name = kind_value["kind"]
+			.as_str()
+			.unwrap_or_default()
+			.to_string();
+		let action_data = kind_value["action_data"].take();
+		let record = sqlx::query!(
+			"
+			INSERT INTO mellow_server_sync_actions (server_id, kind, action_data, criteria, display_name, priority, role_group)
+			VALUES ($1, $2, $3, $4, $5, $6, $7)
+			RETURNING id
+			",
+			server_id.get() as i64,
+			kind_name,
+			action_data,
+			serde_json::to_value(&criteria)?,
+			&display_name,
+			priority,
+			role_group
+		)
+			.fetch_one(&*Pin::static_ref(&PG_POOL).await)
+			.await?;
+
+		Ok(Self {
+			id: record.id.into(),
+			kind,
+			criteria,
+			display_name,
+			priority,
+			role_group
+		})
+	}
+
 	pub async fn get_many(sync_action_ids: &[HakuId<SyncActionMarker>]) -> Result<Vec<Self>> {
 		if sync_action_ids.is_empty() {
 			return Ok(vec![]);
@@ -44,7 +85,7 @@ impl SyncActionModel {
 			.collect();
 		Ok(sqlx::query!(
 			"
-			SELECT id, kind, criteria, action_data, display_name
+			SELECT id, kind, criteria, action_data, display_name, priority, role_group
 			FROM mellow_server_sync_actions
 			WHERE id = ANY($1)
 			",
@@ -59,7 +100,9 @@ impl SyncActionModel {
 						"action_data": record.action_data
 					})).unwrap(),
 					criteria: serde_json::from_value(record.criteria).unwrap(),
-					display_name: record.display_name
+					display_name: record.display_name,
+					priority: record.priority,
+					role_group: record.role_group
 				});
 
 				async move { Ok(acc) }
@@ -69,43 +112,87 @@ impl SyncActionModel {
 	}
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(tag = "kind", content = "action_data")]
 pub enum SyncActionKind {
 	#[serde(rename = "discord.member.assign_roles")]
 	AssignRoles {
 		role_ids: Vec<Id<RoleMarker>>,
-		can_remove: bool
+		can_remove: bool,
+		/// Manual changes to these roles are reverted to what syncing expects.
+		#[serde(default)]
+		enforced: bool
+	},
+	#[serde(rename = "discord.member.remove_roles_except")]
+	RemoveRolesExcept {
+		role_ids: Vec<Id<RoleMarker>>
+	},
+	#[serde(rename = "discord.member.set_nickname")]
+	SetNickname {
+		template: String
+	},
+	#[serde(rename = "discord.member.timeout")]
+	TimeoutMember {
+		duration_minutes: u32,
+		#[serde(default)]
+		reason: Option<String>
 	},
 	#[serde(rename = "discord.member.ban")]
 	BanMember(Reasoning),
 	#[serde(rename = "discord.member.kick")]
 	KickMember(Reasoning),
 	#[serde(rename = "visual_scripting.execute_document")]
-	ExecuteDocument,
+	ExecuteDocument {
+		document_id: HakuId<DocumentMarker>
+	},
 	#[serde(rename = "control_flow.cancel")]
 	ControlFlowCancel(Reasoning)
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Reasoning {
 	#[serde(default)]
-	pub reason: Option<String>/*,
-	pub user_facing_details: Option<String>*/
+	pub reason: Option<String>,
+	/// Shown to the member themselves, unlike `reason` which only ends up in the audit log.
+	#[serde(default)]
+	pub user_facing_details: Option<String>
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Criteria {
 	pub items: Vec<CriteriaItem>,
 	pub quantifier: Quantifier
 }
 
-#[derive(Clone, Debug, Deserialize)]
+impl Criteria {
+	/// Every item that isn't a group or negation, including those nested within one.
+	pub fn leaf_items(&self) -> Vec<&CriteriaItem> {
+		let mut items = vec![];
+		let mut stack: Vec<&CriteriaItem> = self.items.iter().rev().collect();
+		while let Some(item) = stack.pop() {
+			match item {
+				CriteriaItem::Group(criteria) => stack.extend(criteria.items.iter().rev()),
+				CriteriaItem::Not { item } => stack.push(item),
+				_ => items.push(item)
+			}
+		}
+
+		items
+	}
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(tag = "kind", rename_all = "snake_case")]
 pub enum Quantifier {
 	All,
 	AtLeast {
 		value: u8
+	},
+	AtMost {
+		value: u8
+	},
+	Exactly {
+		value: u8
 	}
 }
 
@@ -116,11 +203,67 @@ impl Quantifier {
 			_ => None
 		}
 	}
+
+	pub fn is_met(&self, total_met: usize, total_items: usize) -> bool {
+		match self {
+			Quantifier::All => total_met == total_items,
+			Quantifier::AtLeast { value } => total_met >= *value as usize,
+			Quantifier::AtMost { value } => total_met <= *value as usize,
+			Quantifier::Exactly { value } => total_met == *value as usize
+		}
+	}
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(tag = "kind")]
 pub enum CriteriaItem {
+	#[serde(rename = "discord.member.role")]
+	DiscordMemberHasRole {
+		role_id: Id<RoleMarker>
+	},
+	#[serde(rename = "discord.member.role.missing")]
+	DiscordMemberLacksRole {
+		role_id: Id<RoleMarker>
+	},
+	/// Met when the member's Discord account is older than the given amount of days.
+	#[serde(rename = "discord.user.account_age")]
+	DiscordUserAccountAge {
+		days: u32
+	},
+	/// Met when the member joined the server more than the given amount of days ago.
+	#[serde(rename = "discord.member.membership_age")]
+	DiscordMemberMembershipAge {
+		days: u32
+	},
+	#[serde(rename = "discord.member.boosting")]
+	DiscordMemberBoosting,
+	#[serde(rename = "discord.member.timed_out")]
+	DiscordMemberTimedOut,
+
+	#[serde(rename = "github.organization.membership")]
+	GitHubOrganizationMembership {
+		organization: String
+	},
+	#[serde(rename = "github.repository.contributor")]
+	GitHubRepositoryContributor {
+		owner: String,
+		repository: String
+	},
+	/// Met when the member sponsors the given GitHub user or organisation.
+	#[serde(rename = "github.sponsorship")]
+	GitHubSponsorship {
+		account: String
+	},
+
+	/// Met when the nested criteria are, allowing a different quantifier for part of the items.
+	#[serde(rename = "mellow.criteria.group")]
+	Group(Criteria),
+	/// Met when the wrapped item isn't.
+	#[serde(rename = "mellow.criteria.not")]
+	Not {
+		item: Box<CriteriaItem>
+	},
+
 	#[serde(rename = "hakumi.user.connection")]
 	HakumiUserConnection {
 		connection_kind: ConnectionKind
@@ -152,18 +295,114 @@ pub enum CriteriaItem {
 	PatreonCampaignTierSubscription {
 		campaign_id: String,
 		tier_id: String
+	},
+	#[serde(rename = "patreon.campaign.patron_status")]
+	PatreonCampaignPatronStatus {
+		campaign_id: String,
+		patron_status: PatronStatus
+	},
+	/// Met when the member's current pledge is worth at least the given amount.
+	#[serde(rename = "patreon.campaign.pledge_amount")]
+	PatreonCampaignPledgeAmount {
+		campaign_id: String,
+		minimum_cents: u64
+	},
+	/// Met when the member has given at least the given amount over the whole time they've been a patron.
+	#[serde(rename = "patreon.campaign.lifetime_support")]
+	PatreonCampaignLifetimeSupport {
+		campaign_id: String,
+		minimum_cents: u64
+	},
+	/// Met when the member's current pledge started more than the given amount of days ago.
+	#[serde(rename = "patreon.campaign.pledge_duration")]
+	PatreonCampaignPledgeDuration {
+		campaign_id: String,
+		days: u32
 	}
 }
 
 impl CriteriaItem {
 	pub fn relevant_connection(&self) -> Option<ConnectionKind> {
 		match self {
+			Self::GitHubOrganizationMembership { .. } |
+			Self::GitHubRepositoryContributor { .. } |
+			Self::GitHubSponsorship { .. } => Some(ConnectionKind::GitHub),
 			Self::HakumiUserConnection { connection_kind } => Some(connection_kind.clone()),
-			Self::PatreonCampaignTierSubscription { .. } => Some(ConnectionKind::Patreon),
+			Self::PatreonCampaignTierSubscription { .. } |
+			Self::PatreonCampaignPatronStatus { .. } |
+			Self::PatreonCampaignPledgeAmount { .. } |
+			Self::PatreonCampaignLifetimeSupport { .. } |
+			Self::PatreonCampaignPledgeDuration { .. } => Some(ConnectionKind::Patreon),
 			Self::RobloxGroupMembership { .. } |
 			Self::RobloxGroupMembershipRole { .. } |
 			Self::RobloxGroupMembershipRoleRankInRange { .. } => Some(ConnectionKind::Roblox),
 			_ => None
 		}
 	}
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+	use twilight_model::id::Id;
+
+	use super::{ Criteria, CriteriaItem, Quantifier };
+
+	#[test]
+	fn quantifiers_compare_against_the_amount_met() {
+		assert!(Quantifier::All.is_met(3, 3));
+		assert!(!Quantifier::All.is_met(2, 3));
+		// nothing to meet is trivially met.
+		assert!(Quantifier::All.is_met(0, 0));
+
+		assert!(Quantifier::AtLeast { value: 2 }.is_met(2, 3));
+		assert!(Quantifier::AtLeast { value: 2 }.is_met(3, 3));
+		assert!(!Quantifier::AtLeast { value: 2 }.is_met(1, 3));
+
+		assert!(Quantifier::AtMost { value: 1 }.is_met(0, 3));
+		assert!(Quantifier::AtMost { value: 1 }.is_met(1, 3));
+		assert!(!Quantifier::AtMost { value: 1 }.is_met(2, 3));
+
+		assert!(Quantifier::Exactly { value: 2 }.is_met(2, 3));
+		assert!(!Quantifier::Exactly { value: 2 }.is_met(1, 3));
+		assert!(!Quantifier::Exactly { value: 2 }.is_met(3, 3));
+	}
+
+	#[test]
+	fn only_at_least_has_a_minimum() {
+		assert_eq!(Quantifier::AtLeast { value: 2 }.minimum(), Some(2));
+		assert_eq!(Quantifier::All.minimum(), None);
+		assert_eq!(Quantifier::AtMost { value: 2 }.minimum(), None);
+		assert_eq!(Quantifier::Exactly { value: 2 }.minimum(), None);
+	}
+
+	#[test]
+	fn leaf_items_include_nested_and_negated_items_in_order() {
+		let role = |id: u64| CriteriaItem::DiscordMemberHasRole { role_id: Id::new(id) };
+		let criteria = Criteria {
+			items: vec![
+				role(1),
+				CriteriaItem::Group(Criteria {
+					items: vec![
+						role(2),
+						CriteriaItem::Not { item: Box::new(role(3)) }
+					],
+					quantifier: Quantifier::AtLeast { value: 1 }
+				}),
+				CriteriaItem::Not { item: Box::new(CriteriaItem::Group(Criteria {
+					items: vec![role(4)],
+					quantifier: Quantifier::All
+				})) }
+			],
+			quantifier: Quantifier::All
+		};
+		let role_ids: Vec<u64> = criteria
+			.leaf_items()
+			.into_iter()
+			.filter_map(|x| match x {
+				CriteriaItem::DiscordMemberHasRole { role_id } => Some(role_id.get()),
+				_ => None
+			})
+			.collect();
+		assert_eq!(role_ids, [1, 2, 3, 4]);
+	}
+}
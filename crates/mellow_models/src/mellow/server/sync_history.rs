@@ -0,0 +1,212 @@
+use chrono::{ DateTime, Utc };
+use futures::TryStreamExt;
+use mellow_util::{
+	hakuid::{
+		marker::{ SyncHistoryMarker, SyncJobMarker, UserMarker as HakuUserMarker },
+		HakuId
+	},
+	PG_POOL
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::pin::Pin;
+use twilight_model::id::{
+	marker::{ GuildMarker, UserMarker },
+	Id
+};
+
+use crate::Result;
+
+pub const MAX_PAGE_SIZE: u32 = 100;
+
+/// How long syncs are kept for, see [`SyncHistoryModel::delete_expired`].
+pub const RETENTION_DAYS: i64 = 90;
+
+/// The stored outcome of a single member sync.
+/// `result` holds the serialised sync result as-is, so the shape is owned by mellow itself rather than this table.
+#[derive(Clone, Debug, Serialize)]
+pub struct SyncHistoryModel {
+	pub id: HakuId<SyncHistoryMarker>,
+	pub server_id: Id<GuildMarker>,
+	pub member_id: Id<UserMarker>,
+	pub user_id: Option<HakuId<HakuUserMarker>>,
+	pub sync_job_id: Option<HakuId<SyncJobMarker>>,
+	pub profile_changed: bool,
+	pub result: Value,
+	pub rolled_back_at: Option<DateTime<Utc>>,
+	pub created_at: DateTime<Utc>
+}
+
+#[derive(Default)]
+struct Filter {
+	id: Option<HakuId<SyncHistoryMarker>>,
+	member_id: Option<Id<UserMarker>>,
+	sync_job_id: Option<HakuId<SyncJobMarker>>,
+	before: Option<(DateTime<Utc>, HakuId<SyncHistoryMarker>)>,
+	exclude_rolled_back: bool,
+	exclude_unchanged: bool,
+	limit: Option<u32>
+}
+
+impl SyncHistoryModel {
+	pub async fn create(server_id: Id<GuildMarker>, member_id: Id<UserMarker>, user_id: Option<HakuId<HakuUserMarker>>, sync_job_id: Option<HakuId<SyncJobMarker>>, profile_changed: bool, result: Value) -> Result<()> {
+		sqlx::query!(
+			"
+			INSERT INTO mellow_server_sync_history (server_id, member_id, user_id, sync_job_id, profile_changed, result)
+			VALUES ($1, $2, $3, $4, $5, $6)
+			",
+			server_id.get() as i64,
+			member_id.get() as i64,
+			user_id.map(|x| x.value),
+			sync_job_id.map(|x| x.value),
+			profile_changed,
+			result
+		)
+			.execute(&*Pin::static_ref(&PG_POOL).await)
+			.await?;
+
+		Ok(())
+	}
+
+	pub async fn get(server_id: Id<GuildMarker>, id: HakuId<SyncHistoryMarker>) -> Result<Option<Self>> {
+		Self::find(server_id, Filter { id: Some(id), ..Default::default() })
+			.await
+			.map(|x| x.into_iter().next())
+	}
+
+	/// Returns the most recent syncs of a member, newest first, older pages are fetched by passing the `created_at` and `id` of the last entry as `before`.
+	pub async fn get_member_page(server_id: Id<GuildMarker>, member_id: Id<UserMarker>, before: Option<(DateTime<Utc>, HakuId<SyncHistoryMarker>)>, limit: u32) -> Result<Vec<Self>> {
+		Self::find(server_id, Filter {
+			member_id: Some(member_id),
+			before,
+			limit: Some(limit.min(MAX_PAGE_SIZE)),
+			..Default::default()
+		}).await
+	}
+
+	/// Same as [`Self::get_member_page`], but for every member of the server.
+	pub async fn get_server_page(server_id: Id<GuildMarker>, before: Option<(DateTime<Utc>, HakuId<SyncHistoryMarker>)>, limit: u32) -> Result<Vec<Self>> {
+		Self::find(server_id, Filter {
+			before,
+			limit: Some(limit.min(MAX_PAGE_SIZE)),
+			..Default::default()
+		}).await
+	}
+
+	/// Returns the most recent sync of a member that changed something, and hasn't been rolled back yet.
+	pub async fn get_latest_for_member(server_id: Id<GuildMarker>, member_id: Id<UserMarker>) -> Result<Option<Self>> {
+		Self::find(server_id, Filter {
+			member_id: Some(member_id),
+			exclude_rolled_back: true,
+			exclude_unchanged: true,
+			limit: Some(1),
+			..Default::default()
+		})
+			.await
+			.map(|x| x.into_iter().next())
+	}
+
+	/// Returns every sync from a sync job that hasn't been rolled back yet.
+	pub async fn get_for_sync_job(server_id: Id<GuildMarker>, sync_job_id: HakuId<SyncJobMarker>) -> Result<Vec<Self>> {
+		Self::find(server_id, Filter {
+			sync_job_id: Some(sync_job_id),
+			exclude_rolled_back: true,
+			..Default::default()
+		}).await
+	}
+
+	pub async fn latest_sync_job_id(server_id: Id<GuildMarker>) -> Result<Option<HakuId<SyncJobMarker>>> {
+		Ok(sqlx::query!(
+			"
+			SELECT sync_job_id
+			FROM mellow_server_sync_history
+			WHERE server_id = $1 AND sync_job_id IS NOT NULL
+			ORDER BY created_at DESC
+			LIMIT 1
+			",
+			server_id.get() as i64
+		)
+			.fetch_optional(&*Pin::static_ref(&PG_POOL).await)
+			.await?
+			.and_then(|x| x.sync_job_id)
+			.map(Into::into)
+		)
+	}
+
+	/// Deletes syncs older than [`RETENTION_DAYS`], returning how many were deleted.
+	pub async fn delete_expired() -> Result<u64> {
+		Ok(sqlx::query!(
+			"
+			DELETE FROM mellow_server_sync_history
+			WHERE created_at < $1
+			",
+			Utc::now() - chrono::Duration::days(RETENTION_DAYS)
+		)
+			.execute(&*Pin::static_ref(&PG_POOL).await)
+			.await?
+			.rows_affected()
+		)
+	}
+
+	pub async fn mark_rolled_back(&mut self) -> Result<()> {
+		let record = sqlx::query!(
+			"
+			UPDATE mellow_server_sync_history
+			SET rolled_back_at = now()
+			WHERE id = $1
+			RETURNING rolled_back_at
+			",
+			self.id.value
+		)
+			.fetch_one(&*Pin::static_ref(&PG_POOL).await)
+			.await?;
+		self.rolled_back_at = record.rolled_back_at;
+
+		Ok(())
+	}
+
+	async fn find(server_id: Id<GuildMarker>, filter: Filter) -> Result<Vec<Self>> {
+		Ok(sqlx::query!(
+			"
+			SELECT id, server_id, member_id, user_id, sync_job_id, profile_changed, result, rolled_back_at, created_at
+			FROM mellow_server_sync_history
+			WHERE server_id = $1
+				AND ($2::uuid IS NULL OR id = $2)
+				AND ($3::int8 IS NULL OR member_id = $3)
+				AND ($4::uuid IS NULL OR sync_job_id = $4)
+				AND ($5::timestamptz IS NULL OR (created_at, id) < ($5, $6::uuid))
+				AND (NOT $7 OR rolled_back_at IS NULL)
+				AND (NOT $8 OR profile_changed OR result->>'member_status' <> 'ok')
+			ORDER BY created_at DESC, id DESC
+			LIMIT $9
+			",
+			server_id.get() as i64,
+			filter.id.map(|x| x.value),
+			filter.member_id.map(|x| x.get() as i64),
+			filter.sync_job_id.map(|x| x.value),
+			filter.before.map(|x| x.0),
+			filter.before.map(|x| x.1.value),
+			filter.exclude_rolled_back,
+			filter.exclude_unchanged,
+			filter.limit.map(i64::from)
+		)
+			.fetch(&*Pin::static_ref(&PG_POOL).await)
+			.try_fold(Vec::new(), |mut acc, record| {
+				acc.push(Self {
+					id: record.id.into(),
+					server_id: Id::new(record.server_id as u64),
+					member_id: Id::new(record.member_id as u64),
+					user_id: record.user_id.map(Into::into),
+					sync_job_id: record.sync_job_id.map(Into::into),
+					profile_changed: record.profile_changed,
+					result: record.result,
+					rolled_back_at: record.rolled_back_at,
+					created_at: record.created_at
+				});
+
+				async move { Ok(acc) }
+			})
+			.await?
+		)
+	}
+}
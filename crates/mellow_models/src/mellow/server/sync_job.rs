@@ -0,0 +1,131 @@
+use chrono::{ DateTime, Utc };
+use futures::TryStreamExt;
+use mellow_util::{
+	hakuid::{
+		marker::SyncJobMarker,
+		HakuId
+	},
+	PG_POOL
+};
+use std::pin::Pin;
+use twilight_model::id::{
+	marker::{ GuildMarker, UserMarker },
+	Id
+};
+
+use crate::Result;
+
+/// A server-wide sync that is still in progress, persisted so it can be picked up again after a restart.
+#[derive(Clone, Debug)]
+pub struct SyncJobModel {
+	pub id: HakuId<SyncJobMarker>,
+	pub server_id: Id<GuildMarker>,
+	pub initiator_id: Id<UserMarker>,
+	pub interaction_token: String,
+	pub pending_member_ids: Vec<Id<UserMarker>>,
+	pub total_members: u32,
+	pub total_changed: u32,
+	pub total_failed: u32,
+	pub created_at: DateTime<Utc>
+}
+
+impl SyncJobModel {
+	pub async fn create(server_id: Id<GuildMarker>, initiator_id: Id<UserMarker>, interaction_token: String, member_ids: Vec<Id<UserMarker>>) -> Result<Self> {
+		let pending_member_ids: Vec<i64> = member_ids
+			.iter()
+			.map(|x| x.get() as i64)
+			.collect();
+		let record = sqlx::query!(
+			"
+			INSERT INTO mellow_server_sync_jobs (server_id, initiator_id, interaction_token, pending_member_ids, total_members)
+			VALUES ($1, $2, $3, $4, $5)
+			RETURNING id, created_at
+			",
+			server_id.get() as i64,
+			initiator_id.get() as i64,
+			&interaction_token,
+			&pending_member_ids,
+			member_ids.len() as i32
+		)
+			.fetch_one(&*Pin::static_ref(&PG_POOL).await)
+			.await?;
+
+		Ok(Self {
+			id: record.id.into(),
+			server_id,
+			initiator_id,
+			interaction_token,
+			total_members: member_ids.len() as u32,
+			pending_member_ids: member_ids,
+			total_changed: 0,
+			total_failed: 0,
+			created_at: record.created_at
+		})
+	}
+
+	pub async fn get_all() -> Result<Vec<Self>> {
+		Ok(sqlx::query!(
+			"
+			SELECT id, server_id, initiator_id, interaction_token, pending_member_ids, total_members, total_changed, total_failed, created_at
+			FROM mellow_server_sync_jobs
+			"
+		)
+			.fetch(&*Pin::static_ref(&PG_POOL).await)
+			.try_fold(Vec::new(), |mut acc, record| {
+				acc.push(Self {
+					id: record.id.into(),
+					server_id: Id::new(record.server_id as u64),
+					initiator_id: Id::new(record.initiator_id as u64),
+					interaction_token: record.interaction_token,
+					pending_member_ids: record.pending_member_ids
+						.into_iter()
+						.map(|x| Id::new(x as u64))
+						.collect(),
+					total_members: record.total_members as u32,
+					total_changed: record.total_changed as u32,
+					total_failed: record.total_failed as u32,
+					created_at: record.created_at
+				});
+
+				async move { Ok(acc) }
+			})
+			.await?
+		)
+	}
+
+	pub async fn save_progress(&self) -> Result<()> {
+		let pending_member_ids: Vec<i64> = self.pending_member_ids
+			.iter()
+			.map(|x| x.get() as i64)
+			.collect();
+		sqlx::query!(
+			"
+			UPDATE mellow_server_sync_jobs
+			SET pending_member_ids = $2, total_changed = $3, total_failed = $4
+			WHERE id = $1
+			",
+			self.id.value,
+			&pending_member_ids,
+			self.total_changed as i32,
+			self.total_failed as i32
+		)
+			.execute(&*Pin::static_ref(&PG_POOL).await)
+			.await?;
+
+		Ok(())
+	}
+
+	pub async fn delete(&self) -> Result<()> {
+		sqlx::query!(
+			"
+			DELETE FROM mellow_server_sync_jobs
+			WHERE id = $1
+			",
+			self.id.value
+		)
+			.execute(&*Pin::static_ref(&PG_POOL).await)
+			.await?;
+
+		Ok(())
+	}
+}
@@ -5,7 +5,9 @@ use mellow_util::{
 	},
 	PG_POOL
 };
+use futures::TryStreamExt;
 use serde::Deserialize;
+use serde_json::json;
 use std::pin::Pin;
 use twilight_model::id::{
 	marker::GuildMarker,
@@ -39,15 +41,97 @@ impl UserSettingsModel {
 		} else { Self::default() })
 	}
 
+	/// Returns every server the user has shared the given connection with.
+	pub async fn servers_with_connection(user_id: HakuId<UserMarker>, connection_id: HakuId<ConnectionMarker>) -> Result<Vec<Id<GuildMarker>>> {
+		Ok(sqlx::query!(
+			"
+			SELECT server_id
+			FROM mellow_user_server_settings
+			WHERE user_id = $1 AND user_connections @> $2
+			",
+			user_id.value,
+			json!([{ "id": connection_id }])
+		)
+			.fetch(&*Pin::static_ref(&PG_POOL).await)
+			.try_fold(Vec::new(), |mut acc, record| {
+				acc.push(Id::new(record.server_id as u64));
+				async move { Ok(acc) }
+			})
+			.await?
+		)
+	}
+
 	pub fn user_connections(&self) -> Vec<HakuId<ConnectionMarker>> {
 		self.user_connections
 			.iter()
 			.map(|x| x.id)
 			.collect()
 	}
+
+	/// Picks the user's primary account out of the given connections, which should all be of the same kind.
+	/// Without one, the connection shared with this server first is picked, so the choice never depends on how the connections were loaded.
+	pub fn primary_connection(&self, connection_ids: &[HakuId<ConnectionMarker>]) -> Option<HakuId<ConnectionMarker>> {
+		let mut shared_connections = self.user_connections
+			.iter()
+			.filter(|x| connection_ids.contains(&x.id));
+		let first_connection = shared_connections.next()?;
+		Some(if first_connection.is_primary {
+			first_connection.id
+		} else {
+			shared_connections
+				.find(|x| x.is_primary)
+				.unwrap_or(first_connection)
+				.id
+		})
+	}
 }
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct ConnectionReference {
-	pub id: HakuId<ConnectionMarker>
+	pub id: HakuId<ConnectionMarker>,
+	/// Whether this is the account used for nickname placeholders, when several of the same kind are shared.
+	#[serde(default)]
+	pub is_primary: bool
+}
+
+#[cfg(test)]
+mod tests {
+	use mellow_util::hakuid::{
+		marker::ConnectionMarker,
+		HakuId
+	};
+
+	use super::UserSettingsModel;
+
+	fn id(value: u8) -> HakuId<ConnectionMarker> {
+		serde_json::from_value(serde_json::json!(format!("00000000-0000-0000-0000-{value:012}"))).unwrap()
+	}
+
+	fn user_settings(is_primary: [bool; 3]) -> UserSettingsModel {
+		UserSettingsModel {
+			user_connections: serde_json::from_value(serde_json::json!([
+				// connections shared before primary accounts existed have no is_primary field.
+				if is_primary[0] { serde_json::json!({ "id": id(1), "is_primary": true }) } else { serde_json::json!({ "id": id(1) }) },
+				{ "id": id(2), "is_primary": is_primary[1] },
+				{ "id": id(3), "is_primary": is_primary[2] }
+			])).unwrap()
+		}
+	}
+
+	#[test]
+	fn the_marked_connection_is_primary() {
+		let user_settings = user_settings([false, true, false]);
+		assert_eq!(user_settings.primary_connection(&[id(3), id(2), id(1)]), Some(id(2)));
+		// a primary account of another kind doesn't count.
+		assert_eq!(user_settings.primary_connection(&[id(3), id(1)]), Some(id(1)));
+	}
+
+	#[test]
+	fn the_first_shared_connection_is_primary_without_a_marked_one() {
+		// two roblox accounts, given in the opposite order of how they were shared.
+		let user_settings = user_settings([false, false, false]);
+		assert_eq!(user_settings.primary_connection(&[id(3), id(2)]), Some(id(2)));
+		assert_eq!(user_settings.primary_connection(&[id(2), id(3)]), Some(id(2)));
+		assert_eq!(user_settings.primary_connection(&[]), None);
+	}
 }
\ No newline at end of file
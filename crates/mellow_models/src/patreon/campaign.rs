@@ -4,6 +4,7 @@ use crate::hakumi::visual_scripting::{ Variable, VariableKind };
 
 #[derive(Clone)]
 pub struct CampaignModel {
+	pub id: String,
 	pub tiers: Vec<Tier>
 }
 
@@ -24,9 +25,16 @@ pub struct Tier {
 
 #[derive(Deserialize)]
 pub struct GetCampaign {
+	#[serde(default)]
+	pub data: Vec<CampaignData>,
 	pub included: Option<Vec<IncludedItem>>
 }
 
+#[derive(Deserialize)]
+pub struct CampaignData {
+	pub id: String
+}
+
 #[derive(Deserialize)]
 pub struct IncludedItem {
 	pub attributes: IncludedItemAttributes
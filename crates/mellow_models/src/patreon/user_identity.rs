@@ -1,4 +1,5 @@
-use serde::Deserialize;
+use chrono::{ DateTime, Utc };
+use serde::{ Deserialize, Deserializer, Serialize };
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct UserIdentityModel {
@@ -9,20 +10,52 @@ pub struct UserIdentityModel {
 #[derive(Clone, Debug, Deserialize)]
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum UserIdentityField {
-	Tier,
+	Tier(IncludedTier),
 	Member(Member),
 	Campaign
 }
 
+#[derive(Clone, Debug, Deserialize)]
+pub struct IncludedTier {
+	pub id: String,
+	#[serde(default)]
+	pub attributes: TierAttributes
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct TierAttributes {
+	pub title: Option<String>
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct Member {
 	pub attributes: MemberAttributes,
 	pub relationships: Relationships
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Default, Deserialize)]
 pub struct MemberAttributes {
-	pub patron_status: Option<String>
+	#[serde(default)]
+	pub patron_status: Option<PatronStatus>,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub currently_entitled_amount_cents: u64,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub campaign_lifetime_support_cents: u64,
+	#[serde(default)]
+	pub pledge_relationship_start: Option<DateTime<Utc>>
+}
+
+// patreon sends null instead of leaving fields out, for members that have never pledged.
+fn null_as_default<'de, D: Deserializer<'de>, T: Default + Deserialize<'de>>(deserializer: D) -> Result<T, D::Error> {
+	Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PatronStatus {
+	ActivePatron,
+	DeclinedPatron,
+	FormerPatron
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -47,4 +80,29 @@ pub struct CurrentlyEntitledTier(pub Vec<Tier>);
 #[derive(Clone, Debug, Deserialize)]
 pub struct Tier {
 	pub id: String
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{ MemberAttributes, PatronStatus };
+
+	#[test]
+	fn null_amounts_are_zero() {
+		let attributes: MemberAttributes = serde_json::from_str(r#"{
+			"patron_status": null,
+			"currently_entitled_amount_cents": null,
+			"campaign_lifetime_support_cents": 250,
+			"pledge_relationship_start": null
+		}"#).unwrap();
+		assert_eq!(attributes.currently_entitled_amount_cents, 0);
+		assert_eq!(attributes.campaign_lifetime_support_cents, 250);
+		assert_eq!(attributes.patron_status, None);
+	}
+
+	#[test]
+	fn missing_amounts_are_zero() {
+		let attributes: MemberAttributes = serde_json::from_str(r#"{ "patron_status": "active_patron" }"#).unwrap();
+		assert_eq!(attributes.currently_entitled_amount_cents, 0);
+		assert_eq!(attributes.patron_status, Some(PatronStatus::ActivePatron));
+	}
+}
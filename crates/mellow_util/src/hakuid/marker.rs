@@ -4,4 +4,8 @@ pub struct DocumentMarker;
 
 pub struct SyncActionMarker;
 
+pub struct SyncHistoryMarker;
+
+pub struct SyncJobMarker;
+
 pub struct UserMarker;
\ No newline at end of file
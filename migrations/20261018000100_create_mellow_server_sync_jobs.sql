@@ -0,0 +1,12 @@
+-- server-wide syncs started with /forcesyncall, kept until they finish so they can be resumed after a restart.
+CREATE TABLE mellow_server_sync_jobs (
+	id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+	server_id int8 NOT NULL REFERENCES mellow_servers (id) ON DELETE CASCADE,
+	initiator_id int8 NOT NULL,
+	interaction_token text NOT NULL,
+	pending_member_ids int8[] NOT NULL,
+	total_members int4 NOT NULL,
+	total_changed int4 NOT NULL DEFAULT 0,
+	total_failed int4 NOT NULL DEFAULT 0,
+	created_at timestamptz NOT NULL DEFAULT now()
+);
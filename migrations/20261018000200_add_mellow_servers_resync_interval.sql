@@ -0,0 +1,3 @@
+-- servers without an interval are only synced when something changes.
+ALTER TABLE mellow_servers
+	ADD COLUMN resync_interval_minutes int4 CHECK (resync_interval_minutes > 0);
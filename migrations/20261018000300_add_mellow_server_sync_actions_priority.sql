@@ -0,0 +1,3 @@
+-- lower priorities are applied first, ties are broken by id.
+ALTER TABLE mellow_server_sync_actions
+	ADD COLUMN priority int4 NOT NULL DEFAULT 0;
@@ -0,0 +1,19 @@
+-- the outcome of member syncs that changed something, removed the member or ran into issues.
+CREATE TABLE mellow_server_sync_history (
+	id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+	server_id int8 NOT NULL REFERENCES mellow_servers (id) ON DELETE CASCADE,
+	member_id int8 NOT NULL,
+	user_id uuid REFERENCES users (id) ON DELETE SET NULL,
+	profile_changed bool NOT NULL,
+	result jsonb NOT NULL,
+	created_at timestamptz NOT NULL DEFAULT now()
+);
+
+-- pages are ordered by (created_at, id), for the whole server or a single member.
+CREATE INDEX mellow_server_sync_history_server_id_created_at_idx
+	ON mellow_server_sync_history (server_id, created_at DESC, id DESC);
+CREATE INDEX mellow_server_sync_history_member_id_created_at_idx
+	ON mellow_server_sync_history (server_id, member_id, created_at DESC, id DESC);
+-- expired entries are deleted by age alone.
+CREATE INDEX mellow_server_sync_history_created_at_idx
+	ON mellow_server_sync_history (created_at);
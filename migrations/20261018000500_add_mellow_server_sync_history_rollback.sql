@@ -0,0 +1,8 @@
+ALTER TABLE mellow_server_sync_history
+	ADD COLUMN sync_job_id uuid,
+	ADD COLUMN rolled_back_at timestamptz;
+
+CREATE INDEX mellow_server_sync_history_sync_job_id_idx
+	ON mellow_server_sync_history (server_id, sync_job_id)
+	WHERE sync_job_id IS NOT NULL;
+
@@ -0,0 +1,3 @@
+-- the direct message sent to members before a sync action bans or kicks them, the default one is used when null.
+ALTER TABLE mellow_servers
+	ADD COLUMN removal_message jsonb;
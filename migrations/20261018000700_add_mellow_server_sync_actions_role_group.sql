@@ -0,0 +1,3 @@
+-- only the highest priority met action within a role group assigns its roles.
+ALTER TABLE mellow_server_sync_actions
+	ADD COLUMN role_group text;
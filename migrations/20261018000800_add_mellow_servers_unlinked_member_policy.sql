@@ -0,0 +1,3 @@
+-- how members without a hakumi account are treated, null when the server has no policy for them.
+ALTER TABLE mellow_servers
+	ADD COLUMN unlinked_member_policy jsonb;
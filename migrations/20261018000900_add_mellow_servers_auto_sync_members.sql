@@ -0,0 +1,3 @@
+-- members are synced when they join, or when an account they've shared changes.
+ALTER TABLE mellow_servers
+	ADD COLUMN auto_sync_members bool NOT NULL DEFAULT false;